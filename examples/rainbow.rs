@@ -1,3 +1,4 @@
+#[allow(unused_imports)]
 use imagetext::prelude::*;
 
 fn main() {
@@ -17,7 +18,7 @@ fn main() {
     // let text = "hello my 😓 n🐢ame i☕s 会のすべ aての構成員 nathan and i drink soup boop coop, the quick brown fox jumps over the lazy dog";
 
     // let lines = word_wrap(text, 512, &font, scale(67.0));
-    // let (w, h) = text_size_multiline(&lines, &font, scale(67.0), 1.0);
+    // let (w, h) = text_size_multiline(&lines, &font, scale(67.0), 1.0, &LayoutOptions::default());
 
     // println!("{}x{}", w, h);
 
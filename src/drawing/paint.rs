@@ -15,9 +15,7 @@ pub const WHITE: Paint<'static> = Paint {
 };
 
 pub fn rainbow(start: Point, end: Point) -> Paint<'static> {
-    let mut paint = Paint::default();
-    paint.anti_alias = true;
-    paint.shader = LinearGradient::new(
+    let shader = LinearGradient::new(
         start,
         end,
         vec![
@@ -33,13 +31,16 @@ pub fn rainbow(start: Point, end: Point) -> Paint<'static> {
         Transform::default(),
     )
     .unwrap();
-    paint
+
+    Paint {
+        anti_alias: true,
+        shader,
+        ..Paint::default()
+    }
 }
 
 pub fn ez_gradient(start: Point, end: Point, colors: Vec<Color>) -> Paint<'static> {
-    let mut paint = Paint::default();
-    paint.anti_alias = true;
-    paint.shader = LinearGradient::new(
+    let shader = LinearGradient::new(
         start,
         end,
         colors
@@ -51,19 +52,28 @@ pub fn ez_gradient(start: Point, end: Point, colors: Vec<Color>) -> Paint<'stati
         Transform::default(),
     )
     .unwrap();
-    paint
+
+    Paint {
+        anti_alias: true,
+        shader,
+        ..Paint::default()
+    }
 }
 
 pub fn paint_from_rgba_slice(slice: &[u8; 4]) -> Paint<'static> {
-    let mut paint = Paint::default();
-    paint.anti_alias = true;
+    let mut paint = Paint {
+        anti_alias: true,
+        ..Paint::default()
+    };
     paint.set_color_rgba8(slice[0], slice[1], slice[2], slice[3]);
     paint
 }
 
 pub fn paint_from_rgba(r: u8, g: u8, b: u8, a: u8) -> Paint<'static> {
-    let mut paint = Paint::default();
-    paint.anti_alias = true;
+    let mut paint = Paint {
+        anti_alias: true,
+        ..Paint::default()
+    };
     paint.set_color_rgba8(r, g, b, a);
     paint
 }
@@ -1,12 +1,24 @@
 use tiny_skia::*;
 
 use crate::{
-    measure::text_width, outliner::TextAlign, prelude::WrapStyle, render::render_text_fn,
-    superfont::SuperFont, wrap::text_wrap,
+    fit::text_fit,
+    flow::FlowShape,
+    frames::text_wrap_frames,
+    layout::{paragraph_directions, LayoutOptions, TextLayout},
+    markup::{markup_spans, parse_markup, MarkupFonts},
+    measure::text_height_vertical,
+    outliner::TextAlign,
+    prelude::WrapStyle,
+    render::render_text_fn,
+    rich::{rich_text_wrap, RichLayout, TextSpan},
+    superfont::SuperFont,
+    truncate::{text_truncate, text_wrap_truncated, EllipsisPosition},
+    wrap::text_wrap,
 };
 
 use super::outline::Outline;
 
+#[allow(clippy::too_many_arguments)]
 pub fn draw_text_mut(
     image: &mut image::RgbaImage,
     fill: &Paint,
@@ -22,7 +34,10 @@ pub fn draw_text_mut(
     })
 }
 
-pub fn draw_text_anchored(
+/// Draws a [`TextLayout`] anchored at `x`, `y` by its size (`ax`, `ay` are 0.0 for the
+/// left / top edge and 1.0 for the right / bottom edge).
+#[allow(clippy::too_many_arguments)]
+pub fn draw_text_layout(
     image: &mut image::RgbaImage,
     fill: &Paint,
     outline: Outline,
@@ -30,16 +45,15 @@ pub fn draw_text_anchored(
     y: f32,
     ax: f32,
     ay: f32,
-    scale: rusttype::Scale,
-    font: &SuperFont,
-    text: &str,
+    layout: &TextLayout,
 ) -> Result<(), &'static str> {
     render_text_fn(image, fill, outline, |td| {
-        td.draw_text_anchored(text, x, y, ax, ay, font, scale);
+        td.draw_layout(layout, x - layout.width * ax, y - layout.height * ay);
     })
 }
 
-pub fn draw_text_multiline(
+#[allow(clippy::too_many_arguments)]
+pub fn draw_text_anchored(
     image: &mut image::RgbaImage,
     fill: &Paint,
     outline: Outline,
@@ -47,19 +61,18 @@ pub fn draw_text_multiline(
     y: f32,
     ax: f32,
     ay: f32,
-    width: f32,
     scale: rusttype::Scale,
     font: &SuperFont,
-    lines: &Vec<String>,
-    line_spacing: f32,
-    align: TextAlign,
+    text: &str,
+    options: &LayoutOptions,
 ) -> Result<(), &'static str> {
     render_text_fn(image, fill, outline, |td| {
-        td.draw_text_multiline(lines, x, y, ax, ay, width, font, scale, line_spacing, align);
+        td.draw_text_anchored(text, x, y, ax, ay, font, scale, options);
     })
 }
 
-pub fn draw_text_wrapped(
+#[allow(clippy::too_many_arguments)]
+pub fn draw_text_multiline(
     image: &mut image::RgbaImage,
     fill: &Paint,
     outline: Outline,
@@ -70,15 +83,14 @@ pub fn draw_text_wrapped(
     width: f32,
     scale: rusttype::Scale,
     font: &SuperFont,
-    text: &str,
+    lines: &[String],
     line_spacing: f32,
     align: TextAlign,
-    wrap_style: WrapStyle,
+    options: &LayoutOptions,
 ) -> Result<(), &'static str> {
     render_text_fn(image, fill, outline, |td| {
-        let lines = text_wrap(text, width as i32, font, scale, wrap_style, text_width);
         td.draw_text_multiline(
-            &lines,
+            lines,
             x,
             y,
             ax,
@@ -88,75 +100,92 @@ pub fn draw_text_wrapped(
             scale,
             line_spacing,
             align,
+            options,
         );
     })
 }
 
-#[cfg(feature = "emoji")]
-use crate::emoji::source::EmojiResolver;
-
-#[cfg(feature = "emoji")]
-pub fn draw_parsed_text_mut_with_emojis<R: EmojiResolver>(
+#[allow(clippy::too_many_arguments)]
+pub fn draw_text_wrapped(
     image: &mut image::RgbaImage,
     fill: &Paint,
     outline: Outline,
     x: f32,
     y: f32,
+    ax: f32,
+    ay: f32,
+    width: f32,
     scale: rusttype::Scale,
     font: &SuperFont,
-    emoji_resolver: R,
-    text: &str, // assmes parsed
-    emojis: &[crate::emoji::source::EmojiType],
-    emoji_idx: &mut usize,
+    text: &str,
+    line_spacing: f32,
+    align: TextAlign,
+    wrap_style: WrapStyle,
+    options: &LayoutOptions,
 ) -> Result<(), &'static str> {
-    crate::render::render_text_emoji_fn(
-        image,
-        fill,
-        outline,
+    let layout = TextLayout::wrapped(
+        text,
+        width,
         font,
-        emoji_resolver,
-        emojis.len(),
-        |td, acc| {
-            td.draw_text_with_emojis(text, emojis, emoji_idx, x, y, font, scale, acc);
-        },
-    )
+        scale,
+        line_spacing,
+        align,
+        wrap_style,
+        options,
+    );
+    draw_text_layout(image, fill, outline, x, y, ax, ay, &layout)
 }
 
-#[cfg(feature = "emoji")]
-pub fn draw_text_mut_with_emojis<R: EmojiResolver>(
+/// Draws `text` wrapped at the largest scale between `min_scale` and `max_scale` that fits
+/// in a `width` x `height` box, see [`text_fit`](crate::fit::text_fit).
+#[allow(clippy::too_many_arguments)]
+pub fn draw_text_fit(
     image: &mut image::RgbaImage,
     fill: &Paint,
     outline: Outline,
     x: f32,
     y: f32,
-    scale: rusttype::Scale,
+    ax: f32,
+    ay: f32,
+    width: f32,
+    height: f32,
+    min_scale: f32,
+    max_scale: f32,
     font: &SuperFont,
-    emoji_resolver: R,
-    text: &str, // assmes parsed
+    text: &str,
+    line_spacing: f32,
+    align: TextAlign,
+    wrap_style: WrapStyle,
+    options: &LayoutOptions,
 ) -> Result<(), &'static str> {
-    let (text, emojis) = crate::emoji::parse::parse_out_emojis(
+    let (scale, lines) = text_fit(
         text,
-        font.emoji_options.parse_shortcodes,
-        font.emoji_options.parse_discord_emojis,
+        width,
+        height,
+        min_scale,
+        max_scale,
+        font,
+        line_spacing,
+        wrap_style,
+        options,
     );
 
-    draw_parsed_text_mut_with_emojis(
-        image,
-        fill,
-        outline,
-        x,
-        y,
-        scale,
+    let layout = TextLayout::multiline(
+        &lines,
         font,
-        emoji_resolver,
-        &text,
-        &emojis,
-        &mut 0,
-    )
+        scale,
+        line_spacing,
+        align,
+        Some(width),
+        options,
+    );
+    draw_text_layout(image, fill, outline, x, y, ax, ay, &layout)
 }
 
-#[cfg(feature = "emoji")]
-pub fn draw_parsed_text_anchored_with_emojis<R: EmojiResolver>(
+/// Draws a single line of `text`, shortened with an ellipsis to fit in `max_width`, see
+/// [`text_truncate`].
+#[allow(clippy::too_many_arguments)]
+pub fn draw_text_truncated(
     image: &mut image::RgbaImage,
     fill: &Paint,
     outline: Outline,
@@ -164,30 +193,29 @@ pub fn draw_parsed_text_anchored_with_emojis<R: EmojiResolver>(
     y: f32,
     ax: f32,
     ay: f32,
+    max_width: f32,
     scale: rusttype::Scale,
     font: &SuperFont,
-    emoji_resolver: R,
     text: &str,
-    emojis: &[crate::emoji::source::EmojiType],
-    emoji_idx: &mut usize,
+    position: EllipsisPosition,
+    options: &LayoutOptions,
 ) -> Result<(), &'static str> {
-    crate::render::render_text_emoji_fn(
-        image,
-        fill,
-        outline,
+    let text = text_truncate(
+        text,
+        max_width as i32,
         font,
-        emoji_resolver,
-        emojis.len(),
-        |td, acc| {
-            td.draw_text_anchored_with_emojis(
-                text, emojis, emoji_idx, x, y, ax, ay, font, scale, acc,
-            );
-        },
-    )
+        scale,
+        position,
+        |scale, font, text| TextLayout::ink_width(text, font, scale, options),
+    );
+    let layout = TextLayout::new(&text, font, scale, options);
+    draw_text_layout(image, fill, outline, x, y, ax, ay, &layout)
 }
 
-#[cfg(feature = "emoji")]
-pub fn draw_text_anchored_with_emojis<R: EmojiResolver>(
+/// Draws `text` wrapped to at most `max_lines` lines, see
+/// [`text_wrap_truncated`].
+#[allow(clippy::too_many_arguments)]
+pub fn draw_text_wrapped_truncated(
     image: &mut image::RgbaImage,
     fill: &Paint,
     outline: Outline,
@@ -195,82 +223,193 @@ pub fn draw_text_anchored_with_emojis<R: EmojiResolver>(
     y: f32,
     ax: f32,
     ay: f32,
+    width: f32,
+    max_lines: usize,
     scale: rusttype::Scale,
     font: &SuperFont,
-    emoji_resolver: R,
     text: &str,
+    line_spacing: f32,
+    align: TextAlign,
+    wrap_style: WrapStyle,
+    position: EllipsisPosition,
+    options: &LayoutOptions,
 ) -> Result<(), &'static str> {
-    let (text, emojis) = crate::emoji::parse::parse_out_emojis(
+    let lines = text_wrap_truncated(
         text,
-        font.emoji_options.parse_shortcodes,
-        font.emoji_options.parse_discord_emojis,
-    );
-
-    draw_parsed_text_anchored_with_emojis(
-        image,
-        fill,
-        outline,
-        x,
-        y,
-        ax,
-        ay,
+        width as i32,
+        max_lines,
+        font,
         scale,
+        wrap_style,
+        position,
+        options,
+        |scale, font, text| TextLayout::ink_width(text, font, scale, options),
+    );
+    let layout = TextLayout::multiline(
+        &lines,
         font,
-        emoji_resolver,
-        &text,
-        &emojis,
-        &mut 0,
-    )
+        scale,
+        line_spacing,
+        align,
+        Some(width),
+        options,
+    );
+    draw_text_layout(image, fill, outline, x, y, ax, ay, &layout)
 }
 
-#[cfg(feature = "emoji")]
-pub fn draw_parsed_text_multiline_with_emojis<R: EmojiResolver>(
+/// Flows `text` in `shape` with the top left corner of the shape at `x`, `y`. Each line is
+/// aligned in the span the shape leaves free at its height.
+#[allow(clippy::too_many_arguments)]
+pub fn draw_text_flow(
     image: &mut image::RgbaImage,
     fill: &Paint,
     outline: Outline,
     x: f32,
     y: f32,
-    ax: f32,
-    ay: f32,
-    width: f32,
+    shape: &FlowShape,
     scale: rusttype::Scale,
     font: &SuperFont,
-    emoji_resolver: R,
-    lines: &Vec<String>,
-    emojis: &[crate::emoji::source::EmojiType],
-    emoji_idx: &mut usize,
+    text: &str,
     line_spacing: f32,
     align: TextAlign,
+    wrap_style: WrapStyle,
+    options: &LayoutOptions,
 ) -> Result<(), &'static str> {
-    crate::render::render_text_emoji_fn(
-        image,
-        fill,
-        outline,
+    let layout = TextLayout::flowed(
+        text,
+        shape,
         font,
-        emoji_resolver,
-        emojis.len(),
-        |td, acc| {
-            td.draw_text_multiline_with_emojis(
+        scale,
+        line_spacing,
+        align,
+        wrap_style,
+        options,
+    );
+    draw_text_layout(image, fill, outline, x, y, 0.0, 0.0, &layout)
+}
+
+/// Draws `text` through a chain of frames, see
+/// [`text_wrap_frames`](crate::frames::text_wrap_frames). The lines of every frame are
+/// aligned in its width. Returns the byte offset in `text` where the text that didn't fit
+/// starts.
+#[allow(clippy::too_many_arguments)]
+pub fn draw_text_frames(
+    image: &mut image::RgbaImage,
+    fill: &Paint,
+    outline: Outline,
+    frames: &[rusttype::Rect<f32>],
+    scale: rusttype::Scale,
+    font: &SuperFont,
+    text: &str,
+    line_spacing: f32,
+    align: TextAlign,
+    wrap_style: WrapStyle,
+    options: &LayoutOptions,
+) -> Result<usize, &'static str> {
+    let (lines, stop) = text_wrap_frames(
+        text,
+        frames,
+        font,
+        scale,
+        line_spacing,
+        wrap_style,
+        options,
+        |scale, font, text| TextLayout::ink_width(text, font, scale, options),
+    );
+
+    // lines keep the direction of their paragraph across frames
+    let mut directions =
+        paragraph_directions(text, lines.iter().flatten(), options.white_space).into_iter();
+    let layouts = frames
+        .iter()
+        .zip(&lines)
+        .map(|(frame, lines)| {
+            let layout = TextLayout::multiline_directed(
                 lines,
-                emojis,
-                emoji_idx,
-                x,
-                y,
-                ax,
-                ay,
-                width,
+                directions.by_ref().take(lines.len()).collect(),
                 font,
                 scale,
                 line_spacing,
                 align,
-                acc,
+                Some(frame.width()),
+                options,
             );
-        },
-    )
+            (frame.min, layout)
+        })
+        .collect::<Vec<_>>();
+
+    render_text_fn(image, fill, outline, |td| {
+        for (origin, layout) in &layouts {
+            td.draw_layout(layout, origin.x, origin.y);
+        }
+    })?;
+
+    Ok(stop)
 }
 
-#[cfg(feature = "emoji")]
-pub fn draw_text_multiline_with_emojis<R: EmojiResolver>(
+/// Draws a [`RichLayout`] anchored at `x`, `y` by its size, every span with its own fill
+/// and outline.
+pub fn draw_rich_layout(
+    image: &mut image::RgbaImage,
+    x: f32,
+    y: f32,
+    ax: f32,
+    ay: f32,
+    layout: &RichLayout,
+) -> Result<(), &'static str> {
+    let (x, y) = (x - layout.width * ax, y - layout.height * ay);
+
+    for piece in layout.pieces() {
+        render_text_fn(image, piece.span.fill, piece.span.outline.clone(), |td| {
+            td.draw_layout(&piece.layout, x + piece.x, y + piece.y);
+        })?;
+    }
+    Ok(())
+}
+
+/// Draws spans as one paragraph, breaking lines only at the line breaks of their text.
+#[allow(clippy::too_many_arguments)]
+pub fn draw_rich_text(
+    image: &mut image::RgbaImage,
+    x: f32,
+    y: f32,
+    ax: f32,
+    ay: f32,
+    spans: &[TextSpan],
+    line_spacing: f32,
+    align: TextAlign,
+    options: &LayoutOptions,
+) -> Result<(), &'static str> {
+    let lines = rich_text_wrap(spans, i32::MAX, options);
+    let layout = RichLayout::new(&lines, line_spacing, align, None, options);
+    draw_rich_layout(image, x, y, ax, ay, &layout)
+}
+
+/// Wraps spans to `width` and draws them as one paragraph, see
+/// [`rich_text_wrap`](crate::rich::rich_text_wrap).
+#[allow(clippy::too_many_arguments)]
+pub fn draw_rich_text_wrapped(
+    image: &mut image::RgbaImage,
+    x: f32,
+    y: f32,
+    ax: f32,
+    ay: f32,
+    width: f32,
+    spans: &[TextSpan],
+    line_spacing: f32,
+    align: TextAlign,
+    options: &LayoutOptions,
+) -> Result<(), &'static str> {
+    let lines = rich_text_wrap(spans, width as i32, options);
+    let layout = RichLayout::new(&lines, line_spacing, align, Some(width), options);
+    draw_rich_layout(image, x, y, ax, ay, &layout)
+}
+
+/// Draws `markup` as one paragraph, breaking lines only at the line breaks of its text.
+/// `font` and `scale` style the text outside of tags, see
+/// [`parse_markup`](crate::markup::parse_markup).
+#[allow(clippy::too_many_arguments)]
+pub fn draw_markup(
     image: &mut image::RgbaImage,
     fill: &Paint,
     outline: Outline,
@@ -278,53 +417,32 @@ pub fn draw_text_multiline_with_emojis<R: EmojiResolver>(
     y: f32,
     ax: f32,
     ay: f32,
-    width: f32,
     scale: rusttype::Scale,
-    font: &SuperFont,
-    emoji_resolver: R,
-    lines: &Vec<String>,
+    fonts: &MarkupFonts,
+    markup: &str,
     line_spacing: f32,
     align: TextAlign,
+    options: &LayoutOptions,
 ) -> Result<(), &'static str> {
-    let mut emojis = Vec::new();
-
-    let lines = lines
-        .iter()
-        .map(|l| {
-            let (text, line_emojis) = crate::emoji::parse::parse_out_emojis(
-                l,
-                font.emoji_options.parse_shortcodes,
-                font.emoji_options.parse_discord_emojis,
-            );
-
-            emojis.extend(line_emojis);
-
-            text
-        })
-        .collect();
-
-    draw_parsed_text_multiline_with_emojis(
+    let runs = parse_markup(markup, fonts, scale, fill)?;
+    draw_rich_text(
         image,
-        fill,
-        outline,
         x,
         y,
         ax,
         ay,
-        width,
-        scale,
-        font,
-        emoji_resolver,
-        &lines,
-        &emojis,
-        &mut 0,
+        &markup_spans(&runs, outline),
         line_spacing,
         align,
+        options,
     )
 }
 
-#[cfg(feature = "emoji")]
-pub fn draw_parsed_text_wrapped_with_emojis<R: EmojiResolver>(
+/// Wraps `markup` to `width` and draws it, see
+/// [`parse_markup`](crate::markup::parse_markup) and
+/// [`rich_text_wrap`](crate::rich::rich_text_wrap).
+#[allow(clippy::too_many_arguments)]
+pub fn draw_markup_wrapped(
     image: &mut image::RgbaImage,
     fill: &Paint,
     outline: Outline,
@@ -334,14 +452,164 @@ pub fn draw_parsed_text_wrapped_with_emojis<R: EmojiResolver>(
     ay: f32,
     width: f32,
     scale: rusttype::Scale,
+    fonts: &MarkupFonts,
+    markup: &str,
+    line_spacing: f32,
+    align: TextAlign,
+    options: &LayoutOptions,
+) -> Result<(), &'static str> {
+    let runs = parse_markup(markup, fonts, scale, fill)?;
+    draw_rich_text_wrapped(
+        image,
+        x,
+        y,
+        ax,
+        ay,
+        width,
+        &markup_spans(&runs, outline),
+        line_spacing,
+        align,
+        options,
+    )
+}
+
+/// Draws `text` as a vertical column with its top left corner at `x`, `y`.
+/// Tabs aren't moved to tab stops in vertical text.
+#[allow(clippy::too_many_arguments)]
+pub fn draw_text_vertical(
+    image: &mut image::RgbaImage,
+    fill: &Paint,
+    outline: Outline,
+    x: f32,
+    y: f32,
+    scale: rusttype::Scale,
+    font: &SuperFont,
+    text: &str,
+) -> Result<(), &'static str> {
+    render_text_fn(image, fill, outline, |td| {
+        td.draw_text_vertical(text, x, y, font, scale);
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn draw_text_vertical_multiline(
+    image: &mut image::RgbaImage,
+    fill: &Paint,
+    outline: Outline,
+    x: f32,
+    y: f32,
+    ax: f32,
+    ay: f32,
+    height: f32,
+    scale: rusttype::Scale,
+    font: &SuperFont,
+    lines: &[String],
+    line_spacing: f32,
+    align: TextAlign,
+) -> Result<(), &'static str> {
+    render_text_fn(image, fill, outline, |td| {
+        td.draw_text_vertical_multiline(
+            lines,
+            x,
+            y,
+            ax,
+            ay,
+            height,
+            font,
+            scale,
+            line_spacing,
+            align,
+        );
+    })
+}
+
+/// Wraps `text` into columns no taller than `height` and draws them right-to-left.
+#[allow(clippy::too_many_arguments)]
+pub fn draw_text_vertical_wrapped(
+    image: &mut image::RgbaImage,
+    fill: &Paint,
+    outline: Outline,
+    x: f32,
+    y: f32,
+    ax: f32,
+    ay: f32,
+    height: f32,
+    scale: rusttype::Scale,
     font: &SuperFont,
-    emoji_resolver: R,
     text: &str,
-    emojis: &[crate::emoji::source::EmojiType],
-    emoji_idx: &mut usize,
     line_spacing: f32,
     align: TextAlign,
     wrap_style: WrapStyle,
+) -> Result<(), &'static str> {
+    render_text_fn(image, fill, outline, |td| {
+        let lines = text_wrap(
+            text,
+            height as i32,
+            font,
+            scale,
+            wrap_style,
+            text_height_vertical,
+        );
+        td.draw_text_vertical_multiline(
+            &lines,
+            x,
+            y,
+            ax,
+            ay,
+            height,
+            font,
+            scale,
+            line_spacing,
+            align,
+        );
+    })
+}
+
+#[cfg(feature = "emoji")]
+use crate::emoji::source::EmojiResolver;
+
+/// Draws a [`TextLayout`] anchored at `x`, `y` by its size, resolving the emojis of the
+/// layout with `emoji_resolver`.
+#[cfg(feature = "emoji")]
+#[allow(clippy::too_many_arguments)]
+pub fn draw_text_layout_with_emojis<R: EmojiResolver>(
+    image: &mut image::RgbaImage,
+    fill: &Paint,
+    outline: Outline,
+    x: f32,
+    y: f32,
+    ax: f32,
+    ay: f32,
+    emoji_resolver: R,
+    layout: &TextLayout,
+) -> Result<(), &'static str> {
+    crate::render::render_text_emoji_fn(
+        image,
+        fill,
+        outline,
+        layout.font(),
+        emoji_resolver,
+        layout.glyphs().filter(|g| g.emoji.is_some()).count(),
+        |td, acc| {
+            td.draw_layout_with_emojis(layout, x - layout.width * ax, y - layout.height * ay, acc);
+        },
+    )
+}
+
+#[cfg(feature = "emoji")]
+#[allow(clippy::too_many_arguments)]
+pub fn draw_parsed_text_mut_with_emojis<R: EmojiResolver>(
+    image: &mut image::RgbaImage,
+    fill: &Paint,
+    outline: Outline,
+    x: f32,
+    y: f32,
+    scale: rusttype::Scale,
+    font: &SuperFont,
+    emoji_resolver: R,
+    text: &str, // assmes parsed
+    emojis: &[crate::emoji::source::EmojiType],
+    emoji_idx: &mut usize,
 ) -> Result<(), &'static str> {
     crate::render::render_text_emoji_fn(
         image,
@@ -351,35 +619,81 @@ pub fn draw_parsed_text_wrapped_with_emojis<R: EmojiResolver>(
         emoji_resolver,
         emojis.len(),
         |td, acc| {
-            let lines = text_wrap(
-                text,
-                width as i32,
-                font,
-                scale,
-                wrap_style,
-                crate::measure::parsed_text_width_with_emojis,
-            );
-            td.draw_text_multiline_with_emojis(
-                &lines,
-                emojis,
-                emoji_idx,
-                x,
-                y,
-                ax,
-                ay,
-                width,
-                font,
-                scale,
-                line_spacing,
-                align,
-                acc,
+            td.draw_text_with_emojis(text, emojis, emoji_idx, x, y, font, scale, acc);
+        },
+    )
+}
+
+#[cfg(feature = "emoji")]
+#[allow(clippy::too_many_arguments)]
+pub fn draw_text_mut_with_emojis<R: EmojiResolver>(
+    image: &mut image::RgbaImage,
+    fill: &Paint,
+    outline: Outline,
+    x: f32,
+    y: f32,
+    scale: rusttype::Scale,
+    font: &SuperFont,
+    emoji_resolver: R,
+    text: &str, // assmes parsed
+) -> Result<(), &'static str> {
+    let (text, emojis) = crate::emoji::parse::parse_out_emojis(
+        text,
+        font.emoji_options.parse_shortcodes,
+        font.emoji_options.parse_discord_emojis,
+    );
+
+    draw_parsed_text_mut_with_emojis(
+        image,
+        fill,
+        outline,
+        x,
+        y,
+        scale,
+        font,
+        emoji_resolver,
+        &text,
+        &emojis,
+        &mut 0,
+    )
+}
+
+#[cfg(feature = "emoji")]
+#[allow(clippy::too_many_arguments)]
+pub fn draw_parsed_text_anchored_with_emojis<R: EmojiResolver>(
+    image: &mut image::RgbaImage,
+    fill: &Paint,
+    outline: Outline,
+    x: f32,
+    y: f32,
+    ax: f32,
+    ay: f32,
+    scale: rusttype::Scale,
+    font: &SuperFont,
+    emoji_resolver: R,
+    text: &str,
+    emojis: &[crate::emoji::source::EmojiType],
+    emoji_idx: &mut usize,
+    options: &LayoutOptions,
+) -> Result<(), &'static str> {
+    crate::render::render_text_emoji_fn(
+        image,
+        fill,
+        outline,
+        font,
+        emoji_resolver,
+        emojis.len(),
+        |td, acc| {
+            td.draw_text_anchored_with_emojis(
+                text, emojis, emoji_idx, x, y, ax, ay, font, scale, options, acc,
             );
         },
     )
 }
 
 #[cfg(feature = "emoji")]
-pub fn draw_text_wrapped_with_emojis(
+#[allow(clippy::too_many_arguments)]
+pub fn draw_text_anchored_with_emojis<R: EmojiResolver>(
     image: &mut image::RgbaImage,
     fill: &Paint,
     outline: Outline,
@@ -387,14 +701,11 @@ pub fn draw_text_wrapped_with_emojis(
     y: f32,
     ax: f32,
     ay: f32,
-    width: f32,
     scale: rusttype::Scale,
     font: &SuperFont,
-    emoji_resolver: impl EmojiResolver,
+    emoji_resolver: R,
     text: &str,
-    line_spacing: f32,
-    align: TextAlign,
-    wrap_style: WrapStyle,
+    options: &LayoutOptions,
 ) -> Result<(), &'static str> {
     let (text, emojis) = crate::emoji::parse::parse_out_emojis(
         text,
@@ -402,7 +713,7 @@ pub fn draw_text_wrapped_with_emojis(
         font.emoji_options.parse_discord_emojis,
     );
 
-    draw_parsed_text_wrapped_with_emojis(
+    draw_parsed_text_anchored_with_emojis(
         image,
         fill,
         outline,
@@ -410,15 +721,670 @@ pub fn draw_text_wrapped_with_emojis(
         y,
         ax,
         ay,
-        width,
         scale,
         font,
         emoji_resolver,
         &text,
         &emojis,
         &mut 0,
-        line_spacing,
-        align,
-        wrap_style,
+        options,
+    )
+}
+
+#[cfg(feature = "emoji")]
+#[allow(clippy::too_many_arguments)]
+pub fn draw_parsed_text_multiline_with_emojis<R: EmojiResolver>(
+    image: &mut image::RgbaImage,
+    fill: &Paint,
+    outline: Outline,
+    x: f32,
+    y: f32,
+    ax: f32,
+    ay: f32,
+    width: f32,
+    scale: rusttype::Scale,
+    font: &SuperFont,
+    emoji_resolver: R,
+    lines: &[String],
+    emojis: &[crate::emoji::source::EmojiType],
+    emoji_idx: &mut usize,
+    line_spacing: f32,
+    align: TextAlign,
+    options: &LayoutOptions,
+) -> Result<(), &'static str> {
+    crate::render::render_text_emoji_fn(
+        image,
+        fill,
+        outline,
+        font,
+        emoji_resolver,
+        emojis.len(),
+        |td, acc| {
+            td.draw_text_multiline_with_emojis(
+                lines,
+                emojis,
+                emoji_idx,
+                x,
+                y,
+                ax,
+                ay,
+                width,
+                font,
+                scale,
+                line_spacing,
+                align,
+                options,
+                acc,
+            );
+        },
     )
 }
+
+#[cfg(feature = "emoji")]
+#[allow(clippy::too_many_arguments)]
+pub fn draw_text_multiline_with_emojis<R: EmojiResolver>(
+    image: &mut image::RgbaImage,
+    fill: &Paint,
+    outline: Outline,
+    x: f32,
+    y: f32,
+    ax: f32,
+    ay: f32,
+    width: f32,
+    scale: rusttype::Scale,
+    font: &SuperFont,
+    emoji_resolver: R,
+    lines: &[String],
+    line_spacing: f32,
+    align: TextAlign,
+    options: &LayoutOptions,
+) -> Result<(), &'static str> {
+    let mut emojis = Vec::new();
+
+    let lines: Vec<String> = lines
+        .iter()
+        .map(|l| {
+            let (text, line_emojis) = crate::emoji::parse::parse_out_emojis(
+                l,
+                font.emoji_options.parse_shortcodes,
+                font.emoji_options.parse_discord_emojis,
+            );
+
+            emojis.extend(line_emojis);
+
+            text
+        })
+        .collect();
+
+    draw_parsed_text_multiline_with_emojis(
+        image,
+        fill,
+        outline,
+        x,
+        y,
+        ax,
+        ay,
+        width,
+        scale,
+        font,
+        emoji_resolver,
+        &lines,
+        &emojis,
+        &mut 0,
+        line_spacing,
+        align,
+        options,
+    )
+}
+
+#[cfg(feature = "emoji")]
+#[allow(clippy::too_many_arguments)]
+pub fn draw_parsed_text_wrapped_with_emojis<R: EmojiResolver>(
+    image: &mut image::RgbaImage,
+    fill: &Paint,
+    outline: Outline,
+    x: f32,
+    y: f32,
+    ax: f32,
+    ay: f32,
+    width: f32,
+    scale: rusttype::Scale,
+    font: &SuperFont,
+    emoji_resolver: R,
+    text: &str,
+    emojis: &[crate::emoji::source::EmojiType],
+    emoji_idx: &mut usize,
+    line_spacing: f32,
+    align: TextAlign,
+    wrap_style: WrapStyle,
+    options: &LayoutOptions,
+) -> Result<(), &'static str> {
+    let layout = TextLayout::parsed_wrapped_with_emojis(
+        text,
+        emojis,
+        emoji_idx,
+        width,
+        font,
+        scale,
+        line_spacing,
+        align,
+        wrap_style,
+        options,
+    );
+    draw_text_layout_with_emojis(image, fill, outline, x, y, ax, ay, emoji_resolver, &layout)
+}
+
+#[cfg(feature = "emoji")]
+#[allow(clippy::too_many_arguments)]
+pub fn draw_text_wrapped_with_emojis(
+    image: &mut image::RgbaImage,
+    fill: &Paint,
+    outline: Outline,
+    x: f32,
+    y: f32,
+    ax: f32,
+    ay: f32,
+    width: f32,
+    scale: rusttype::Scale,
+    font: &SuperFont,
+    emoji_resolver: impl EmojiResolver,
+    text: &str,
+    line_spacing: f32,
+    align: TextAlign,
+    wrap_style: WrapStyle,
+    options: &LayoutOptions,
+) -> Result<(), &'static str> {
+    let (text, emojis) = crate::emoji::parse::parse_out_emojis(
+        text,
+        font.emoji_options.parse_shortcodes,
+        font.emoji_options.parse_discord_emojis,
+    );
+
+    draw_parsed_text_wrapped_with_emojis(
+        image,
+        fill,
+        outline,
+        x,
+        y,
+        ax,
+        ay,
+        width,
+        scale,
+        font,
+        emoji_resolver,
+        &text,
+        &emojis,
+        &mut 0,
+        line_spacing,
+        align,
+        wrap_style,
+        options,
+    )
+}
+
+#[cfg(feature = "emoji")]
+#[allow(clippy::too_many_arguments)]
+pub fn draw_text_fit_with_emojis(
+    image: &mut image::RgbaImage,
+    fill: &Paint,
+    outline: Outline,
+    x: f32,
+    y: f32,
+    ax: f32,
+    ay: f32,
+    width: f32,
+    height: f32,
+    min_scale: f32,
+    max_scale: f32,
+    font: &SuperFont,
+    emoji_resolver: impl EmojiResolver,
+    text: &str,
+    line_spacing: f32,
+    align: TextAlign,
+    wrap_style: WrapStyle,
+    options: &LayoutOptions,
+) -> Result<(), &'static str> {
+    let (scale, lines) = crate::fit::text_fit_with_emojis(
+        text,
+        width,
+        height,
+        min_scale,
+        max_scale,
+        font,
+        line_spacing,
+        wrap_style,
+        options,
+    );
+
+    let layout = TextLayout::multiline_with_emojis(
+        &lines,
+        font,
+        scale,
+        line_spacing,
+        align,
+        Some(width),
+        options,
+    );
+    draw_text_layout_with_emojis(image, fill, outline, x, y, ax, ay, emoji_resolver, &layout)
+}
+
+#[cfg(feature = "emoji")]
+#[allow(clippy::too_many_arguments)]
+pub fn draw_text_truncated_with_emojis(
+    image: &mut image::RgbaImage,
+    fill: &Paint,
+    outline: Outline,
+    x: f32,
+    y: f32,
+    ax: f32,
+    ay: f32,
+    max_width: f32,
+    scale: rusttype::Scale,
+    font: &SuperFont,
+    emoji_resolver: impl EmojiResolver,
+    text: &str,
+    position: EllipsisPosition,
+    options: &LayoutOptions,
+) -> Result<(), &'static str> {
+    let (text, emojis) = crate::emoji::parse::parse_out_emojis(
+        text,
+        font.emoji_options.parse_shortcodes,
+        font.emoji_options.parse_discord_emojis,
+    );
+    let (text, emojis) = crate::truncate::parsed_text_truncate_with_emojis(
+        &text,
+        &emojis,
+        max_width as i32,
+        font,
+        scale,
+        position,
+        options,
+    );
+
+    let layout = TextLayout::parsed_with_emojis(&text, &emojis, &mut 0, font, scale, options);
+    draw_text_layout_with_emojis(image, fill, outline, x, y, ax, ay, emoji_resolver, &layout)
+}
+
+#[cfg(feature = "emoji")]
+#[allow(clippy::too_many_arguments)]
+pub fn draw_text_wrapped_truncated_with_emojis(
+    image: &mut image::RgbaImage,
+    fill: &Paint,
+    outline: Outline,
+    x: f32,
+    y: f32,
+    ax: f32,
+    ay: f32,
+    width: f32,
+    max_lines: usize,
+    scale: rusttype::Scale,
+    font: &SuperFont,
+    emoji_resolver: impl EmojiResolver,
+    text: &str,
+    line_spacing: f32,
+    align: TextAlign,
+    wrap_style: WrapStyle,
+    position: EllipsisPosition,
+    options: &LayoutOptions,
+) -> Result<(), &'static str> {
+    let (text, emojis) = crate::emoji::parse::parse_out_emojis(
+        text,
+        font.emoji_options.parse_shortcodes,
+        font.emoji_options.parse_discord_emojis,
+    );
+    let (lines, emojis) = crate::truncate::parsed_text_wrap_truncated_with_emojis(
+        &text,
+        &emojis,
+        width as i32,
+        max_lines,
+        font,
+        scale,
+        wrap_style,
+        position,
+        options,
+    );
+
+    let layout = TextLayout::parsed_multiline_with_emojis(
+        &lines,
+        &emojis,
+        &mut 0,
+        font,
+        scale,
+        line_spacing,
+        align,
+        Some(width),
+        options,
+    );
+    draw_text_layout_with_emojis(image, fill, outline, x, y, ax, ay, emoji_resolver, &layout)
+}
+
+#[cfg(feature = "emoji")]
+#[allow(clippy::too_many_arguments)]
+pub fn draw_text_flow_with_emojis(
+    image: &mut image::RgbaImage,
+    fill: &Paint,
+    outline: Outline,
+    x: f32,
+    y: f32,
+    shape: &FlowShape,
+    scale: rusttype::Scale,
+    font: &SuperFont,
+    emoji_resolver: impl EmojiResolver,
+    text: &str,
+    line_spacing: f32,
+    align: TextAlign,
+    wrap_style: WrapStyle,
+    options: &LayoutOptions,
+) -> Result<(), &'static str> {
+    let layout = TextLayout::flowed_with_emojis(
+        text,
+        shape,
+        font,
+        scale,
+        line_spacing,
+        align,
+        wrap_style,
+        options,
+    );
+    draw_text_layout_with_emojis(
+        image,
+        fill,
+        outline,
+        x,
+        y,
+        0.0,
+        0.0,
+        emoji_resolver,
+        &layout,
+    )
+}
+
+#[cfg(feature = "emoji")]
+#[allow(clippy::too_many_arguments)]
+pub fn draw_text_frames_with_emojis(
+    image: &mut image::RgbaImage,
+    fill: &Paint,
+    outline: Outline,
+    frames: &[rusttype::Rect<f32>],
+    scale: rusttype::Scale,
+    font: &SuperFont,
+    emoji_resolver: impl EmojiResolver,
+    text: &str,
+    line_spacing: f32,
+    align: TextAlign,
+    wrap_style: WrapStyle,
+    options: &LayoutOptions,
+) -> Result<usize, &'static str> {
+    let (lines, stop) = text_wrap_frames(
+        text,
+        frames,
+        font,
+        scale,
+        line_spacing,
+        wrap_style,
+        options,
+        |scale, font, text| TextLayout::ink_width_with_emojis(text, font, scale, options),
+    );
+
+    let mut directions =
+        paragraph_directions(text, lines.iter().flatten(), options.white_space).into_iter();
+    let layouts = frames
+        .iter()
+        .zip(&lines)
+        .map(|(frame, lines)| {
+            let layout = TextLayout::multiline_directed_with_emojis(
+                lines,
+                directions.by_ref().take(lines.len()).collect(),
+                font,
+                scale,
+                line_spacing,
+                align,
+                Some(frame.width()),
+                options,
+            );
+            (frame.min, layout)
+        })
+        .collect::<Vec<_>>();
+
+    let emoji_count = layouts
+        .iter()
+        .map(|(_, layout)| layout.glyphs().filter(|g| g.emoji.is_some()).count())
+        .sum();
+
+    crate::render::render_text_emoji_fn(
+        image,
+        fill,
+        outline,
+        font,
+        emoji_resolver,
+        emoji_count,
+        |td, acc| {
+            for (origin, layout) in &layouts {
+                td.draw_layout_with_emojis(layout, origin.x, origin.y, acc);
+            }
+        },
+    )?;
+
+    Ok(stop)
+}
+
+/// Draws a [`RichLayout`] laid out with emojis, resolving the emojis of every span with
+/// `emoji_resolver`.
+#[cfg(feature = "emoji")]
+pub fn draw_rich_layout_with_emojis(
+    image: &mut image::RgbaImage,
+    x: f32,
+    y: f32,
+    ax: f32,
+    ay: f32,
+    mut emoji_resolver: impl EmojiResolver,
+    layout: &RichLayout,
+) -> Result<(), &'static str> {
+    let (x, y) = (x - layout.width * ax, y - layout.height * ay);
+
+    for piece in layout.pieces() {
+        crate::render::render_text_emoji_fn(
+            image,
+            piece.span.fill,
+            piece.span.outline.clone(),
+            piece.layout.font(),
+            &mut emoji_resolver,
+            piece.layout.glyphs().filter(|g| g.emoji.is_some()).count(),
+            |td, acc| {
+                td.draw_layout_with_emojis(&piece.layout, x + piece.x, y + piece.y, acc);
+            },
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "emoji")]
+#[allow(clippy::too_many_arguments)]
+pub fn draw_rich_text_with_emojis(
+    image: &mut image::RgbaImage,
+    x: f32,
+    y: f32,
+    ax: f32,
+    ay: f32,
+    emoji_resolver: impl EmojiResolver,
+    spans: &[TextSpan],
+    line_spacing: f32,
+    align: TextAlign,
+    options: &LayoutOptions,
+) -> Result<(), &'static str> {
+    let lines = crate::rich::rich_text_wrap_with_emojis(spans, i32::MAX, options);
+    let layout = RichLayout::new_with_emojis(&lines, line_spacing, align, None, options);
+    draw_rich_layout_with_emojis(image, x, y, ax, ay, emoji_resolver, &layout)
+}
+
+#[cfg(feature = "emoji")]
+#[allow(clippy::too_many_arguments)]
+pub fn draw_rich_text_wrapped_with_emojis(
+    image: &mut image::RgbaImage,
+    x: f32,
+    y: f32,
+    ax: f32,
+    ay: f32,
+    width: f32,
+    emoji_resolver: impl EmojiResolver,
+    spans: &[TextSpan],
+    line_spacing: f32,
+    align: TextAlign,
+    options: &LayoutOptions,
+) -> Result<(), &'static str> {
+    let lines = crate::rich::rich_text_wrap_with_emojis(spans, width as i32, options);
+    let layout = RichLayout::new_with_emojis(&lines, line_spacing, align, Some(width), options);
+    draw_rich_layout_with_emojis(image, x, y, ax, ay, emoji_resolver, &layout)
+}
+
+#[cfg(feature = "emoji")]
+#[allow(clippy::too_many_arguments)]
+pub fn draw_markup_with_emojis(
+    image: &mut image::RgbaImage,
+    fill: &Paint,
+    outline: Outline,
+    x: f32,
+    y: f32,
+    ax: f32,
+    ay: f32,
+    scale: rusttype::Scale,
+    fonts: &MarkupFonts,
+    emoji_resolver: impl EmojiResolver,
+    markup: &str,
+    line_spacing: f32,
+    align: TextAlign,
+    options: &LayoutOptions,
+) -> Result<(), &'static str> {
+    let runs = parse_markup(markup, fonts, scale, fill)?;
+    draw_rich_text_with_emojis(
+        image,
+        x,
+        y,
+        ax,
+        ay,
+        emoji_resolver,
+        &markup_spans(&runs, outline),
+        line_spacing,
+        align,
+        options,
+    )
+}
+
+#[cfg(feature = "emoji")]
+#[allow(clippy::too_many_arguments)]
+pub fn draw_markup_wrapped_with_emojis(
+    image: &mut image::RgbaImage,
+    fill: &Paint,
+    outline: Outline,
+    x: f32,
+    y: f32,
+    ax: f32,
+    ay: f32,
+    width: f32,
+    scale: rusttype::Scale,
+    fonts: &MarkupFonts,
+    emoji_resolver: impl EmojiResolver,
+    markup: &str,
+    line_spacing: f32,
+    align: TextAlign,
+    options: &LayoutOptions,
+) -> Result<(), &'static str> {
+    let runs = parse_markup(markup, fonts, scale, fill)?;
+    draw_rich_text_wrapped_with_emojis(
+        image,
+        x,
+        y,
+        ax,
+        ay,
+        width,
+        emoji_resolver,
+        &markup_spans(&runs, outline),
+        line_spacing,
+        align,
+        options,
+    )
+}
+
+#[test]
+fn draw_text_anchored_test() {
+    use crate::outliner::AnchorMode;
+
+    let font = SuperFont::new(crate::superfont::static_test_font(), vec![]);
+    let scale = rusttype::Scale::uniform(40.0);
+
+    // the lowest inked row of the first glyph, drawn anchored by its bottom at y = 80
+    let baseline = |text: &str, options: &LayoutOptions| {
+        let mut image = image::RgbaImage::new(200, 100);
+        draw_text_anchored(
+            &mut image,
+            &Paint::default(),
+            Outline::None,
+            10.0,
+            80.0,
+            0.0,
+            1.0,
+            scale,
+            &font,
+            text,
+            options,
+        )
+        .unwrap();
+
+        let left = (0..image.width())
+            .find(|&x| (0..image.height()).any(|y| image.get_pixel(x, y)[3] > 0))
+            .unwrap();
+        (left..left + 5)
+            .flat_map(|x| (0..image.height()).map(move |y| (x, y)))
+            .filter(|&(x, y)| image.get_pixel(x, y)[3] > 127)
+            .map(|(_, y)| y as i32)
+            .max()
+            .unwrap()
+    };
+
+    // by their ink, "Agy" is raised above its descenders
+    let ink = LayoutOptions::default();
+    assert!(baseline("ace", &ink) - baseline("Agy", &ink) > 5);
+
+    let metrics = LayoutOptions::new().with_anchor_mode(AnchorMode::Metrics);
+    assert!((baseline("ace", &metrics) - baseline("Agy", &metrics)).abs() <= 1);
+}
+
+#[test]
+fn draw_text_wrapped_test() {
+    use crate::wrap::WhiteSpace;
+
+    let font = SuperFont::new(crate::superfont::static_test_font(), vec![]);
+    let scale = rusttype::Scale::uniform(20.0);
+
+    // the lowest inked row of "a" and "b" drawn wrapped
+    let rows = |white_space| {
+        let mut image = image::RgbaImage::new(200, 100);
+        draw_text_wrapped(
+            &mut image,
+            &Paint::default(),
+            Outline::None,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            200.0,
+            scale,
+            &font,
+            "a\n\nb",
+            1.0,
+            TextAlign::Left,
+            WrapStyle::Word,
+            &LayoutOptions::new().with_white_space(white_space),
+        )
+        .unwrap();
+
+        (0..image.height())
+            .filter(|&y| (0..image.width()).any(|x| image.get_pixel(x, y)[3] > 0))
+            .max()
+            .unwrap()
+    };
+
+    // the line breaks are kept, "b" goes two lines down
+    assert!(rows(WhiteSpace::Normal) < 20);
+    assert!(rows(WhiteSpace::PreLine) > 40);
+}
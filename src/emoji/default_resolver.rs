@@ -9,7 +9,7 @@ use super::{
     source::{EmojiResolver, ResolvedEmoji, UnresolvedEmoji},
 };
 
-static CLIENT: Lazy<reqwest::blocking::Client> = Lazy::new(|| reqwest::blocking::Client::new());
+static CLIENT: Lazy<reqwest::blocking::Client> = Lazy::new(reqwest::blocking::Client::new);
 static LOCAL_CACHE: Lazy<RwLock<HashMap<String, Option<image::RgbaImage>>>> =
     Lazy::new(|| RwLock::new(HashMap::default()));
 static EXTERNAL_CACHE: Lazy<Cache<String, Option<image::RgbaImage>>> = Lazy::new(|| {
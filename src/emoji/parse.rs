@@ -69,11 +69,11 @@ static EMOJI_MAP: Lazy<HashMap<String, &'static str>> = Lazy::new(|| {
 
 static EMOJI_UNICODE_RE_STR: Lazy<String> = Lazy::new(|| {
     let mut emojis = emojis::iter()
-        .map(|e| format!("{}", regex::escape(e.as_str())))
+        .map(|e| regex::escape(e.as_str()))
         .collect::<Vec<_>>();
 
-    emojis.sort_by(|a, b| b.len().cmp(&a.len()));
-    format!("{}", emojis.join("|"))
+    emojis.sort_by_key(|e| std::cmp::Reverse(e.len()));
+    emojis.join("|")
 });
 
 static EMOJI_SHORT_CODES_RE_STR: &str = r":[a-zA-Z0-9_\+\\-]{1,32}:";
@@ -101,7 +101,7 @@ static EMOJI_UNICODE_RE: Lazy<Regex> =
     Lazy::new(|| Regex::new(&EMOJI_UNICODE_RE_STR).expect("Failed to compile emoji regex"));
 
 static EMOJI_SHORT_CODES_RE: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(&EMOJI_SHORT_CODES_RE_STR).expect("Failed to compile emoji shortcode regex")
+    Regex::new(EMOJI_SHORT_CODES_RE_STR).expect("Failed to compile emoji shortcode regex")
 });
 
 static DISCORD_EMOJI_RE: Lazy<Regex> = Lazy::new(|| {
@@ -165,8 +165,8 @@ fn parse_emoji(s: &str) -> Option<EmojiType> {
     None
 }
 
-pub fn parse_out_emojis<'t>(
-    text: &'t str,
+pub fn parse_out_emojis(
+    text: &str,
     parse_shortcodes: bool,
     parse_discord_emojis: bool,
 ) -> (String, Vec<EmojiType>) {
@@ -250,12 +250,12 @@ pub fn clean_emojis(text: &str) -> String {
     for cap in TEXT_TOKEN_RE.find_iter(text) {
         let s = cap.as_str();
 
-        if let Some(_) = parse_emoji(s) {
+        if parse_emoji(s).is_some() {
             parsed.push(PLACEHOLDER_EMOJI);
             continue;
         }
 
-        if let Some(_) = parse_discord_emoji(s) {
+        if parse_discord_emoji(s).is_some() {
             parsed.push(PLACEHOLDER_EMOJI);
             continue;
         }
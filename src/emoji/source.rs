@@ -118,9 +118,17 @@ pub struct ResolvedEmoji {
 }
 
 pub trait EmojiResolver {
+    #[allow(clippy::ptr_arg)]
     fn resolve(&mut self, emojis: &Vec<UnresolvedEmoji>) -> Vec<ResolvedEmoji>;
 }
 
+// lets a resolver be used for several draws
+impl<R: EmojiResolver + ?Sized> EmojiResolver for &mut R {
+    fn resolve(&mut self, emojis: &Vec<UnresolvedEmoji>) -> Vec<ResolvedEmoji> {
+        (**self).resolve(emojis)
+    }
+}
+
 #[test]
 pub fn emoji_src() {
     let src = EmojiSource::Dir(String::from("test"));
@@ -0,0 +1,115 @@
+use std::str::FromStr;
+
+/// OpenType feature settings (`liga`, `smcp`, `tnum`, `ss01`...) applied to the whole text
+/// when it is shaped.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FontFeatures {
+    features: Vec<rustybuzz::Feature>,
+}
+
+impl FontFeatures {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the value of a feature, `0` turns it off, `1` turns it on and larger values
+    /// pick an alternate (for features like `aalt` or `salt`).
+    pub fn set(mut self, tag: &str, value: u32) -> Self {
+        let tag = rustybuzz::Tag::from_bytes_lossy(tag.as_bytes());
+
+        self.features.retain(|f| f.tag != tag);
+        self.features.push(rustybuzz::Feature::new(tag, value, ..));
+        self
+    }
+
+    pub fn enable(self, tag: &str) -> Self {
+        self.set(tag, 1)
+    }
+
+    pub fn disable(self, tag: &str) -> Self {
+        self.set(tag, 0)
+    }
+
+    /// Parses a comma or whitespace separated list of features, e.g. `"tnum, -liga, ss01"`
+    /// or `"smcp salt=2"`.
+    pub fn parse(s: &str) -> Result<Self, &'static str> {
+        s.split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|f| !f.is_empty())
+            .try_fold(Self::new(), |features, f| {
+                let f = rustybuzz::Feature::from_str(f)?;
+                let tag = f.tag.to_bytes();
+                let tag = std::str::from_utf8(&tag).map_err(|_| "Invalid feature tag")?;
+
+                Ok(features.set(tag, f.value))
+            })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.features.is_empty()
+    }
+
+    pub(crate) fn as_slice(&self) -> &[rustybuzz::Feature] {
+        &self.features
+    }
+}
+
+impl FromStr for FontFeatures {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+#[test]
+fn font_features_test() {
+    let features = FontFeatures::parse("tnum, -liga ss01=1 salt=2").unwrap();
+
+    assert_eq!(
+        features,
+        FontFeatures::new()
+            .enable("tnum")
+            .disable("liga")
+            .enable("ss01")
+            .set("salt", 2)
+    );
+
+    // later settings override earlier ones
+    assert_eq!(
+        FontFeatures::parse("liga,-liga").unwrap(),
+        FontFeatures::new().disable("liga")
+    );
+
+    assert!(FontFeatures::parse("liga=maybe").is_err());
+}
+
+#[test]
+fn font_features_shaping_test() {
+    use crate::{
+        layout::{LayoutOptions, TextLayout},
+        superfont::SuperFont,
+    };
+
+    let font = SuperFont::new(crate::superfont::static_test_font(), vec![]);
+    let scale = rusttype::Scale::uniform(100.0);
+    let width =
+        |font: &SuperFont<'_>| TextLayout::new("To", font, scale, &LayoutOptions::default()).width;
+
+    // "To" is kerned by default, turning `kern` off spreads it back out
+    let kerned = width(&font);
+    let unkerned = width(
+        &font
+            .clone()
+            .with_features(FontFeatures::new().disable("kern")),
+    );
+    assert!(unkerned - kerned > 10.0);
+
+    assert_eq!(
+        width(
+            &font
+                .clone()
+                .with_features(FontFeatures::new().enable("kern"))
+        ),
+        kerned
+    );
+}
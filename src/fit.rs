@@ -0,0 +1,173 @@
+use crate::{
+    layout::{LayoutOptions, TextLayout},
+    outliner::TextAlign,
+    superfont::SuperFont,
+    wrap::{text_wrap_with_options, WrapStyle},
+};
+
+// the search stops once the scale is known to within this many pixels
+const FIT_PRECISION: f32 = 0.5;
+
+/// Finds the largest uniform scale between `min_scale` and `max_scale` at which `text`,
+/// wrapped with `wrap_style`, fits in a `width` x `height` box. Returns the scale and the
+/// wrapped lines, or `min_scale` and its lines when the text doesn't fit at any scale.
+/// The text is wrapped and measured with `options`.
+#[allow(clippy::too_many_arguments)]
+pub fn text_fit(
+    text: &str,
+    width: f32,
+    height: f32,
+    min_scale: f32,
+    max_scale: f32,
+    font: &SuperFont,
+    line_spacing: f32,
+    wrap_style: WrapStyle,
+    options: &LayoutOptions,
+) -> (rusttype::Scale, Vec<String>) {
+    fit(min_scale, max_scale, |scale| {
+        let lines = text_wrap_with_options(
+            text,
+            width as i32,
+            font,
+            scale,
+            wrap_style,
+            options,
+            |scale, font, text| TextLayout::ink_width(text, font, scale, options),
+        );
+        let (w, h) = TextLayout::multiline(
+            &lines,
+            font,
+            scale,
+            line_spacing,
+            TextAlign::Start,
+            None,
+            options,
+        )
+        .size();
+
+        (w as f32 <= width && h as f32 <= height, lines)
+    })
+}
+
+#[cfg(feature = "emoji")]
+/// Same as [`text_fit`], with emojis measured as emojis. The returned lines still contain
+/// the emojis (and shortcodes) and can be drawn with `draw_text_multiline_with_emojis`.
+#[allow(clippy::too_many_arguments)]
+pub fn text_fit_with_emojis(
+    text: &str,
+    width: f32,
+    height: f32,
+    min_scale: f32,
+    max_scale: f32,
+    font: &SuperFont,
+    line_spacing: f32,
+    wrap_style: WrapStyle,
+    options: &LayoutOptions,
+) -> (rusttype::Scale, Vec<String>) {
+    fit(min_scale, max_scale, |scale| {
+        let lines = text_wrap_with_options(
+            text,
+            width as i32,
+            font,
+            scale,
+            wrap_style,
+            options,
+            |scale, font, text| TextLayout::ink_width_with_emojis(text, font, scale, options),
+        );
+        let (w, h) = TextLayout::multiline_with_emojis(
+            &lines,
+            font,
+            scale,
+            line_spacing,
+            TextAlign::Start,
+            None,
+            options,
+        )
+        .size();
+
+        (w as f32 <= width && h as f32 <= height, lines)
+    })
+}
+
+// binary search for the largest scale that fits, `layout` returns whether the text fits
+// at a scale and its lines
+fn fit(
+    min_scale: f32,
+    max_scale: f32,
+    layout: impl Fn(rusttype::Scale) -> (bool, Vec<String>),
+) -> (rusttype::Scale, Vec<String>) {
+    let (min_scale, max_scale) = (min_scale.min(max_scale), max_scale.max(min_scale));
+
+    let (fits, lines) = layout(rusttype::Scale::uniform(max_scale));
+    if fits {
+        return (rusttype::Scale::uniform(max_scale), lines);
+    }
+
+    let (mut lo, mut hi) = (min_scale, max_scale);
+    let mut best = None;
+
+    while hi - lo > FIT_PRECISION {
+        let mid = (lo + hi) / 2.0;
+
+        match layout(rusttype::Scale::uniform(mid)) {
+            (true, lines) => {
+                lo = mid;
+                best = Some(lines);
+            }
+            (false, _) => hi = mid,
+        }
+    }
+
+    let scale = rusttype::Scale::uniform(lo);
+    (scale, best.unwrap_or_else(|| layout(scale).1))
+}
+
+#[cfg(feature = "emoji")]
+#[test]
+fn text_fit_test() {
+    let font = SuperFont::new(crate::emoji::EMOJI_FONT.clone(), vec![]);
+    let text = "11 22 33 44 55 66";
+
+    let options = LayoutOptions::default();
+    let (scale, lines) = text_fit(
+        text,
+        100.0,
+        100.0,
+        5.0,
+        200.0,
+        &font,
+        1.0,
+        WrapStyle::Word,
+        &options,
+    );
+    let (w, h) = crate::measure::text_size_multiline(&lines, &font, scale, 1.0, &options);
+
+    assert!(scale.y > 5.0 && scale.y < 200.0);
+    assert!(w <= 100 && h <= 100);
+
+    // a bit larger doesn't fit
+    let larger = rusttype::Scale::uniform(scale.y + FIT_PRECISION);
+    let lines = crate::wrap::text_wrap(
+        text,
+        100,
+        &font,
+        larger,
+        WrapStyle::Word,
+        crate::measure::text_width,
+    );
+    let (w, h) = crate::measure::text_size_multiline(&lines, &font, larger, 1.0, &options);
+    assert!(w > 100 || h > 100);
+
+    let (scale, _) = text_fit(
+        "1",
+        100.0,
+        100.0,
+        5.0,
+        20.0,
+        &font,
+        1.0,
+        WrapStyle::Word,
+        &options,
+    );
+    assert_eq!(scale.y, 20.0);
+}
@@ -0,0 +1,390 @@
+use tiny_skia::{Path, PathSegment, Point, Rect};
+
+use crate::{
+    layout::LayoutOptions,
+    superfont::SuperFont,
+    wrap::{
+        collapse_spaces, paragraphs, soft_hyphens, spaced_words, WhiteSpace, WrapStyle, Wrappable,
+    },
+};
+
+/// The area text flows in: a column, or the inside of a path (a circle, a badge...), with
+/// rectangles (an avatar, an image...) that text flows around. Coordinates are relative to
+/// the top left corner of the text, where the first line starts.
+#[derive(Debug, Clone)]
+pub struct FlowShape {
+    area: Area,
+    exclusions: Vec<Rect>,
+}
+
+#[derive(Debug, Clone)]
+enum Area {
+    // from x = 0 to the width, as tall as the text
+    Column(f32),
+    // flattened closed contours filled with the even-odd rule, and their bounds
+    Contours(Vec<Vec<Point>>, Rect),
+}
+
+impl FlowShape {
+    /// A column `width` pixels wide, as tall as the text needs.
+    pub fn column(width: f32) -> Self {
+        Self {
+            area: Area::Column(width),
+            exclusions: Vec::new(),
+        }
+    }
+
+    /// The inside of `path`. Text that doesn't fit in it is left out.
+    pub fn path(path: &Path) -> Self {
+        Self {
+            area: Area::Contours(flatten(path), path.bounds()),
+            exclusions: Vec::new(),
+        }
+    }
+
+    /// Keeps text out of `rect`.
+    pub fn exclude(mut self, rect: Rect) -> Self {
+        self.exclusions.push(rect);
+        self
+    }
+
+    /// The widest horizontal span that is free from `top` to `bottom`, as `(x, width)`.
+    pub fn span(&self, top: f32, bottom: f32) -> Option<(f32, f32)> {
+        let mut spans = match &self.area {
+            Area::Column(width) => vec![(0.0, *width)],
+            Area::Contours(contours, _) => {
+                // the spans that are inside at the top, middle and bottom of the line
+                let inset = (bottom - top) * 0.01;
+                [top + inset, (top + bottom) / 2.0, bottom - inset]
+                    .into_iter()
+                    .map(|y| inside(contours, y))
+                    .reduce(|a, b| intersect(&a, &b))
+                    .unwrap_or_default()
+            }
+        };
+
+        for rect in &self.exclusions {
+            if rect.top() < bottom && rect.bottom() > top {
+                spans = spans
+                    .into_iter()
+                    .flat_map(|(x0, x1)| [(x0, x1.min(rect.left())), (x0.max(rect.right()), x1)])
+                    .filter(|(x0, x1)| x1 > x0)
+                    .collect();
+            }
+        }
+
+        spans
+            .into_iter()
+            .map(|(x0, x1)| (x0, x1 - x0))
+            .filter(|(_, width)| *width > 0.0)
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    fn width(&self) -> f32 {
+        match &self.area {
+            Area::Column(width) => *width,
+            Area::Contours(_, bounds) => bounds.width(),
+        }
+    }
+
+    fn bottom(&self) -> f32 {
+        match &self.area {
+            Area::Column(_) => f32::INFINITY,
+            Area::Contours(_, bounds) => bounds.bottom(),
+        }
+    }
+}
+
+/// A line of text flowed in a [`FlowShape`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlowLine {
+    pub text: String,
+    /// left edge of the free span of the line
+    pub x: f32,
+    pub top: f32,
+    /// width of the free span of the line
+    pub width: f32,
+}
+
+/// Wraps `text` into lines that each fit in the span `shape` leaves free at their height.
+/// Lines go down by `line_spacing` times `scale.y`, lines where the next word doesn't fit
+/// are skipped when wider lines can come later. `WrapStyle::Optimal` breaks greedily.
+/// Whitespace is handled according to the [`WhiteSpace`] mode of `options`.
+#[allow(clippy::too_many_arguments)]
+pub fn text_flow(
+    text: &str,
+    shape: &FlowShape,
+    font: &SuperFont,
+    scale: rusttype::Scale,
+    line_spacing: f32,
+    wrap_style: WrapStyle,
+    options: &LayoutOptions,
+    width_fn: impl Fn(rusttype::Scale, &SuperFont, &str) -> i32,
+) -> Vec<FlowLine> {
+    let mut lines = Vec::new();
+    if shape.width() <= 0.0 {
+        return lines;
+    }
+
+    let line_height = scale.y * line_spacing;
+    let white_space = options.white_space;
+    let preserve = matches!(white_space, WhiteSpace::Pre | WhiteSpace::PreWrap);
+    let paragraphs = match (white_space, wrap_style) {
+        (WhiteSpace::Normal, WrapStyle::Unicode) => paragraphs(text).collect(),
+        (WhiteSpace::Normal, _) => vec![text],
+        _ => paragraphs(text).collect::<Vec<_>>(),
+    };
+
+    let mut top = 0.0;
+    for paragraph in paragraphs {
+        let collapsed;
+        let words = match wrap_style {
+            WrapStyle::Unicode if preserve => segments(paragraph),
+            WrapStyle::Unicode => {
+                collapsed = collapse_spaces(paragraph);
+                segments(&collapsed)
+            }
+            _ if white_space == WhiteSpace::Pre => vec![paragraph],
+            _ if preserve => spaced_words(paragraph),
+            _ => paragraph.split_whitespace().collect(),
+        };
+
+        let chars_mode = preserve || matches!(wrap_style, WrapStyle::Unicode);
+        let mut breaker = words
+            .into_iter()
+            .wrap_lines(0, font, scale, chars_mode, &width_fn)
+            .preserve_spaces(preserve)
+            .break_words(matches!(wrap_style, WrapStyle::Character));
+        if let WrapStyle::Hyphenate(language) = wrap_style {
+            breaker = breaker.hyphenate(language);
+        }
+
+        let mut empty = true;
+        loop {
+            if top + scale.y > shape.bottom() {
+                return lines;
+            }
+
+            // lines that don't go down can't get past where the next line doesn't fit
+            let Some((x, width)) = shape.span(top, top + scale.y) else {
+                if line_height <= 0.0 {
+                    return lines;
+                }
+                top += line_height;
+                continue;
+            };
+
+            breaker.set_width(match white_space {
+                WhiteSpace::Pre => i32::MAX,
+                _ => width as i32,
+            });
+            if width < shape.width() && !breaker.next_fits() {
+                if line_height <= 0.0 {
+                    return lines;
+                }
+                top += line_height;
+                continue;
+            }
+
+            let text = match breaker.next() {
+                Some(line) => soft_hyphens(line),
+                // an empty line keeps its height
+                None if empty && white_space != WhiteSpace::Normal => String::new(),
+                None => break,
+            };
+
+            lines.push(FlowLine {
+                text,
+                x,
+                top,
+                width,
+            });
+            top += line_height;
+            empty = false;
+        }
+    }
+
+    lines
+}
+
+// the segments of `text` between break opportunities, with their trailing spaces
+fn segments(text: &str) -> Vec<&str> {
+    let mut start = 0;
+    unicode_linebreak::linebreaks(text)
+        .map(|(end, _)| {
+            let segment = &text[start..end];
+            start = end;
+            segment
+        })
+        .collect()
+}
+
+// the contours of `path` as polygons
+fn flatten(path: &Path) -> Vec<Vec<Point>> {
+    const STEPS: usize = 16;
+
+    let mut contours: Vec<Vec<Point>> = Vec::new();
+    let mut last = Point::zero();
+
+    for segment in path.segments() {
+        let points = match segment {
+            PathSegment::MoveTo(p) => {
+                contours.push(Vec::new());
+                vec![p]
+            }
+            PathSegment::LineTo(p) => vec![p],
+            PathSegment::QuadTo(p1, p2) => (1..=STEPS)
+                .map(|i| {
+                    let t = i as f32 / STEPS as f32;
+                    let u = 1.0 - t;
+                    let (a, b, c) = (u * u, 2.0 * u * t, t * t);
+                    Point::from_xy(
+                        a * last.x + b * p1.x + c * p2.x,
+                        a * last.y + b * p1.y + c * p2.y,
+                    )
+                })
+                .collect(),
+            PathSegment::CubicTo(p1, p2, p3) => (1..=STEPS)
+                .map(|i| {
+                    let t = i as f32 / STEPS as f32;
+                    let u = 1.0 - t;
+                    let (a, b, c, d) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+                    Point::from_xy(
+                        a * last.x + b * p1.x + c * p2.x + d * p3.x,
+                        a * last.y + b * p1.y + c * p2.y + d * p3.y,
+                    )
+                })
+                .collect(),
+            PathSegment::Close => continue,
+        };
+
+        last = points.last().copied().unwrap_or(last);
+        if let Some(contour) = contours.last_mut() {
+            contour.extend(points);
+        }
+    }
+
+    contours
+}
+
+// the spans of the horizontal line at `y` that are inside the contours (even-odd)
+fn inside(contours: &[Vec<Point>], y: f32) -> Vec<(f32, f32)> {
+    let mut crossings = contours
+        .iter()
+        .flat_map(|contour| {
+            // every contour is closed
+            let edges = contour.iter().zip(contour.iter().cycle().skip(1));
+            edges
+                .filter(|(a, b)| (a.y <= y) != (b.y <= y))
+                .map(|(a, b)| a.x + (y - a.y) / (b.y - a.y) * (b.x - a.x))
+        })
+        .collect::<Vec<_>>();
+
+    crossings.sort_by(f32::total_cmp);
+    crossings.chunks_exact(2).map(|c| (c[0], c[1])).collect()
+}
+
+// the spans that are in both `a` and `b`, both sorted
+fn intersect(a: &[(f32, f32)], b: &[(f32, f32)]) -> Vec<(f32, f32)> {
+    let mut spans = Vec::new();
+    let (mut i, mut j) = (0, 0);
+
+    while i < a.len() && j < b.len() {
+        let (x0, x1) = (a[i].0.max(b[j].0), a[i].1.min(b[j].1));
+        if x1 > x0 {
+            spans.push((x0, x1));
+        }
+
+        if a[i].1 < b[j].1 {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    spans
+}
+
+#[cfg(feature = "emoji")]
+#[test]
+fn text_flow_test() {
+    let font = SuperFont::new(crate::emoji::EMOJI_FONT.clone(), vec![]);
+    let scale = rusttype::Scale::uniform(20.0);
+    let width = crate::measure::text_width(scale, &font, "11 22 11");
+
+    // an avatar in the top left corner, two lines tall
+    let shape =
+        FlowShape::column(width as f32).exclude(Rect::from_xywh(0.0, 0.0, 50.0, 35.0).unwrap());
+    assert_eq!(shape.span(0.0, 20.0), Some((50.0, width as f32 - 50.0)));
+    assert_eq!(shape.span(40.0, 60.0), Some((0.0, width as f32)));
+
+    let lines = text_flow(
+        "11 22 11 22 11 22",
+        &shape,
+        &font,
+        scale,
+        1.0,
+        WrapStyle::Word,
+        &LayoutOptions::default(),
+        crate::measure::text_width,
+    );
+    let texts = lines.iter().map(|l| l.text.as_str()).collect::<Vec<_>>();
+    assert_eq!(texts, vec!["11 22", "11 22", "11 22"]);
+    assert_eq!((lines[0].x, lines[0].top), (50.0, 0.0));
+    assert_eq!((lines[2].x, lines[2].top), (0.0, 40.0));
+
+    // laid out, the first line starts right of the avatar
+    let layout = crate::layout::TextLayout::flowed(
+        "11 22 11 22 11 22",
+        &shape,
+        &font,
+        scale,
+        1.0,
+        crate::outliner::TextAlign::Left,
+        WrapStyle::Word,
+        &LayoutOptions::default(),
+    );
+    assert!(layout.glyphs().next().unwrap().glyph.position().x >= 50.0);
+    assert_eq!(layout.size(), (width, 60));
+
+    // a circle: lines are narrower at the top and the bottom, text that doesn't fit is
+    // left out
+    let circle = FlowShape::path(&tiny_skia::PathBuilder::from_circle(50.0, 50.0, 50.0).unwrap());
+    let (x, width) = circle.span(40.0, 60.0).unwrap();
+    assert!((x - 1.0).abs() < 1.0 && (width - 98.0).abs() < 2.0);
+    assert!(circle.span(0.0, 20.0).unwrap().1 < 80.0);
+
+    let lines = text_flow(
+        &"1 ".repeat(100),
+        &circle,
+        &font,
+        scale,
+        1.0,
+        WrapStyle::Word,
+        &LayoutOptions::default(),
+        crate::measure::text_width,
+    );
+    // the chords at the very top and bottom are too short for a word
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0].top, 20.0);
+    assert!(lines.iter().all(|l| l.x >= 0.0 && l.x + l.width <= 100.0));
+
+    // lines that don't go down stop where the shape leaves no room
+    let shapes = [
+        FlowShape::column(100.0).exclude(Rect::from_xywh(0.0, 0.0, 100.0, 30.0).unwrap()),
+        // a span too narrow for the next word
+        FlowShape::column(100.0).exclude(Rect::from_xywh(0.0, 0.0, 90.0, 30.0).unwrap()),
+    ];
+    for (shape, line_spacing) in shapes.iter().flat_map(|s| [(s, 0.0), (s, -1.0)]) {
+        let lines = text_flow(
+            "1111 2222",
+            shape,
+            &font,
+            scale,
+            line_spacing,
+            WrapStyle::Word,
+            &LayoutOptions::default(),
+            crate::measure::text_width,
+        );
+        assert!(lines.is_empty());
+    }
+}
@@ -4,7 +4,7 @@ use hashbrown::HashMap;
 use once_cell::sync::Lazy;
 use rusttype::Font;
 
-use crate::prelude::SuperFont;
+use crate::prelude::{FontVariations, SuperFont};
 
 static FONT_DB: Lazy<RwLock<HashMap<String, Font<'static>>>> =
     Lazy::new(|| RwLock::new(HashMap::default()));
@@ -26,7 +26,7 @@ impl FontDB {
                 db.insert(name.into(), font);
                 Ok(())
             }
-            Err(_) => return Err("Failed to write to font database"),
+            Err(_) => Err("Failed to write to font database"),
         }
     }
 
@@ -59,25 +59,23 @@ impl FontDB {
             }
         };
 
-        for entry in dir {
-            if let Ok(entry) = entry {
-                let path = entry.path();
-                if path.is_file() {
-                    match path.extension().and_then(|ext| ext.to_str()) {
-                        Some("ttf") | Some("otf") | Some("TTF") | Some("OTF") => {
-                            match path.file_stem().and_then(|stem| stem.to_str()) {
-                                Some(name) => match Self::load_from_path(name, &path) {
-                                    Ok(_) => log::info!("Loaded font: {}", name),
-                                    Err(e) => log::warn!("Failed to load font: {}", e),
-                                },
-                                None => log::warn!("Failed to load font: {}", path.display()),
-                            }
+        for entry in dir.flatten() {
+            let path = entry.path();
+            if path.is_file() {
+                match path.extension().and_then(|ext| ext.to_str()) {
+                    Some("ttf") | Some("otf") | Some("TTF") | Some("OTF") => {
+                        match path.file_stem().and_then(|stem| stem.to_str()) {
+                            Some(name) => match Self::load_from_path(name, &path) {
+                                Ok(_) => log::info!("Loaded font: {}", name),
+                                Err(e) => log::warn!("Failed to load font: {}", e),
+                            },
+                            None => log::warn!("Failed to load font: {}", path.display()),
                         }
-                        _ => (),
                     }
-                } else if path.is_dir() {
-                    Self::load_from_dir(path);
+                    _ => (),
                 }
+            } else if path.is_dir() {
+                Self::load_from_dir(path);
             }
         }
     }
@@ -121,10 +119,7 @@ impl FontDB {
 
     pub fn get(name: &str) -> Option<Font<'static>> {
         match FONT_DB.read() {
-            Ok(db) => match db.get(name) {
-                Some(font) => Some(font.clone()),
-                None => None,
-            },
+            Ok(db) => db.get(name).cloned(),
             Err(_) => None,
         }
     }
@@ -132,18 +127,18 @@ impl FontDB {
     pub fn superfont<'a>(font_names: &[&str]) -> Option<SuperFont<'a>> {
         match FONT_DB.read() {
             Ok(db) => {
-                let mut fonts = font_names.into_iter().filter_map(|name| db.get(*name));
+                let mut fonts = font_names.iter().filter_map(|name| db.get(*name));
 
                 #[cfg(not(feature = "emoji"))]
                 return Some(SuperFont::new(
                     fonts.next()?.clone(),
-                    fonts.map(|f| f.clone()).collect::<Vec<Font<'static>>>(),
+                    fonts.cloned().collect::<Vec<Font<'static>>>(),
                 ));
 
                 #[cfg(feature = "emoji")]
                 return Some(SuperFont::with_emoji_options(
                     fonts.next()?.clone(),
-                    fonts.map(|f| f.clone()).collect::<Vec<Font<'static>>>(),
+                    fonts.cloned().collect::<Vec<Font<'static>>>(),
                     DEFAULT_EMOJI_OPTIONS
                         .read()
                         .expect("Failed to read emoji options")
@@ -158,6 +153,24 @@ impl FontDB {
         Self::superfont(&query.split_whitespace().collect::<Vec<&str>>())
     }
 
+    /// Like [`FontDB::superfont`], with variable font axis values applied to the fonts.
+    pub fn superfont_with_variations<'a>(
+        font_names: &[&str],
+        variations: &FontVariations,
+    ) -> Option<SuperFont<'a>> {
+        Self::superfont(font_names).map(|font| font.with_variations(variations))
+    }
+
+    pub fn query_with_variations<'a>(
+        query: &str,
+        variations: &FontVariations,
+    ) -> Option<SuperFont<'a>> {
+        Self::superfont_with_variations(
+            &query.split_whitespace().collect::<Vec<&str>>(),
+            variations,
+        )
+    }
+
     pub fn remove(name: &str) -> Result<(), &'static str> {
         match FONT_DB.write() {
             Ok(mut db) => {
@@ -175,11 +188,11 @@ impl FontDB {
     ) -> Option<SuperFont<'a>> {
         match FONT_DB.read() {
             Ok(db) => {
-                let mut fonts = font_names.into_iter().filter_map(|name| db.get(*name));
+                let mut fonts = font_names.iter().filter_map(|name| db.get(*name));
 
                 Some(SuperFont::with_emoji_options(
                     fonts.next()?.clone(),
-                    fonts.map(|f| f.clone()).collect::<Vec<Font<'static>>>(),
+                    fonts.cloned().collect::<Vec<Font<'static>>>(),
                     emoji_options,
                 ))
             }
@@ -200,9 +213,8 @@ impl FontDB {
 
     #[cfg(feature = "emoji")]
     pub fn set_default_emoji_options(emoji_options: crate::prelude::EmojiOptions) {
-        match DEFAULT_EMOJI_OPTIONS.write() {
-            Ok(mut options) => *options = emoji_options,
-            Err(_) => {}
+        if let Ok(mut options) = DEFAULT_EMOJI_OPTIONS.write() {
+            *options = emoji_options;
         }
     }
 }
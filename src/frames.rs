@@ -0,0 +1,176 @@
+use crate::{
+    hit_test::source_offsets,
+    layout::LayoutOptions,
+    outliner::AnchorMode,
+    superfont::SuperFont,
+    wrap::{wrap_lines_limited, WhiteSpace, WrapStyle},
+};
+
+/// Wraps `text` through a chain of frames (columns, pages...): every frame is filled with as
+/// many lines as fit in its height, at its width, and the text goes on in the next one.
+/// Returns the lines of every frame, and the byte offset in `text` where the text that
+/// didn't fit in any frame starts (`text.len()` when it all fits), which can be passed on
+/// to more frames.
+#[allow(clippy::too_many_arguments)]
+pub fn text_wrap_frames(
+    text: &str,
+    frames: &[rusttype::Rect<f32>],
+    font: &SuperFont,
+    scale: rusttype::Scale,
+    line_spacing: f32,
+    wrap_style: WrapStyle,
+    options: &LayoutOptions,
+    width_fn: impl Fn(rusttype::Scale, &SuperFont, &str) -> i32,
+) -> (Vec<Vec<String>>, usize) {
+    let preserve = matches!(options.white_space, WhiteSpace::Pre | WhiteSpace::PreWrap);
+    let mut start = 0;
+
+    let frames = frames
+        .iter()
+        .map(|frame| {
+            let rest = &text[start..];
+            // blank lines only go away when whitespace is collapsed
+            let done = match options.white_space {
+                WhiteSpace::Normal => rest.trim().is_empty(),
+                _ => rest.is_empty(),
+            };
+            if done {
+                start = text.len();
+                return Vec::new();
+            }
+
+            // one more line than fits tells whether the rest of the text does
+            let count = frame_lines(frame.height(), font, scale, line_spacing, options);
+            let mut lines = wrap_lines_limited(
+                rest,
+                frame.width() as i32,
+                count.saturating_add(1),
+                font,
+                scale,
+                wrap_style,
+                options,
+                &width_fn,
+            );
+            if lines.len() <= count {
+                start = text.len();
+                return lines;
+            }
+
+            lines.truncate(count);
+            if let Some(end) = source_offsets(rest, lines.iter().map(String::as_str), preserve)
+                .last()
+                .and_then(|map| map.last())
+            {
+                start += end + next_line(&rest[*end..]);
+            }
+            lines
+        })
+        .collect();
+
+    (frames, start)
+}
+
+// the number of lines that fit in `height`, measured like `text_size_multiline`
+fn frame_lines(
+    height: f32,
+    font: &SuperFont,
+    scale: rusttype::Scale,
+    line_spacing: f32,
+    options: &LayoutOptions,
+) -> usize {
+    let first = match options.anchor_mode {
+        AnchorMode::Ink => scale.y,
+        AnchorMode::Metrics => {
+            let v_metrics = font.main.v_metrics(scale);
+            v_metrics.ascent - v_metrics.descent
+        }
+    };
+
+    let line_height = scale.y * line_spacing;
+    if height < first {
+        0
+    } else if line_height <= 0.0 {
+        usize::MAX
+    } else {
+        ((height - first) / line_height) as usize + 1
+    }
+}
+
+// the length of the whitespace a line ends with in `text`: up to its line break if it has
+// one, indentation after it belongs to the next line
+fn next_line(text: &str) -> usize {
+    let space = text.len() - text.trim_start().len();
+
+    match text[..space].find('\n') {
+        Some(i) => i + 1,
+        None => space,
+    }
+}
+
+#[cfg(feature = "emoji")]
+#[test]
+fn text_wrap_frames_test() {
+    let font = SuperFont::new(crate::emoji::EMOJI_FONT.clone(), vec![]);
+    let scale = rusttype::Scale::uniform(20.0);
+    let width = crate::measure::text_width(scale, &font, "11 22") as f32;
+    let frame = |x: f32, height: f32| rusttype::Rect {
+        min: rusttype::point(x, 0.0),
+        max: rusttype::point(x + width, height),
+    };
+
+    let text = "11 22 11 22 11 22 11 22 11";
+    let wrap = |frames: &[rusttype::Rect<f32>]| {
+        text_wrap_frames(
+            text,
+            frames,
+            &font,
+            scale,
+            1.0,
+            WrapStyle::Word,
+            &LayoutOptions::default(),
+            crate::measure::text_width,
+        )
+    };
+
+    // two lines fit in 45 pixels, one in 30
+    let (frames, stop) = wrap(&[frame(0.0, 45.0), frame(100.0, 30.0)]);
+    assert_eq!(frames, vec![vec!["11 22", "11 22"], vec!["11 22"]]);
+    assert_eq!(&text[stop..], "11 22 11");
+
+    let (frames, stop) = wrap(&[frame(0.0, 45.0), frame(100.0, 10.0), frame(200.0, 100.0)]);
+    assert_eq!(frames[1], Vec::<String>::new());
+    assert_eq!(frames[2], vec!["11 22", "11 22", "11"]);
+    assert_eq!(stop, text.len());
+
+    // hard line breaks in the middle of a frame chain are kept
+    let options = LayoutOptions::new().with_white_space(WhiteSpace::PreLine);
+    let text = "11\n\n22\n11";
+    let (frames, stop) = text_wrap_frames(
+        text,
+        &[frame(0.0, 20.0), frame(0.0, 40.0)],
+        &font,
+        scale,
+        1.0,
+        WrapStyle::Word,
+        &options,
+        crate::measure::text_width,
+    );
+    assert_eq!(frames, vec![vec!["11"], vec!["", "22"]]);
+    assert_eq!(&text[stop..], "11");
+
+    // trailing blank lines are kept when whitespace is
+    let options = LayoutOptions::new().with_white_space(WhiteSpace::PreWrap);
+    let text = "11\n\n\n";
+    let (frames, stop) = text_wrap_frames(
+        text,
+        &[frame(0.0, 20.0), frame(0.0, 100.0)],
+        &font,
+        scale,
+        1.0,
+        WrapStyle::Word,
+        &options,
+        crate::measure::text_width,
+    );
+    assert_eq!(frames, vec![vec!["11"], vec!["", "", ""]]);
+    assert_eq!(stop, text.len());
+}
@@ -0,0 +1,610 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{
+    hyphenation::SOFT_HYPHEN,
+    layout::{LayoutLine, LayoutOptions, TextLayout},
+    outliner::TextAlign,
+    superfont::SuperFont,
+    wrap::{WhiteSpace, WrapStyle},
+};
+
+/// A caret position in a [`TextLayout`]: a line and a byte offset into the text of that
+/// line, on a grapheme boundary.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TextPosition {
+    pub line: usize,
+    pub index: usize,
+}
+
+// a grapheme and the horizontal span it covers in the layout
+struct GraphemeBox {
+    start: usize,
+    end: usize,
+    x0: f32,
+    x1: f32,
+    rtl: bool,
+}
+
+impl GraphemeBox {
+    // the edge the caret sits at before the grapheme
+    #[inline]
+    fn leading(&self) -> f32 {
+        if self.rtl {
+            self.x1
+        } else {
+            self.x0
+        }
+    }
+
+    #[inline]
+    fn trailing(&self) -> f32 {
+        if self.rtl {
+            self.x0
+        } else {
+            self.x1
+        }
+    }
+}
+
+// splits a line into graphemes, the graphemes of a cluster (e.g. a ligature) share its
+// advance equally
+fn grapheme_boxes(line: &LayoutLine) -> Vec<GraphemeBox> {
+    let mut starts = line.glyphs.iter().map(|g| g.cluster).collect::<Vec<_>>();
+    starts.sort_unstable();
+    starts.dedup();
+
+    let mut boxes = Vec::with_capacity(starts.len());
+    let mut pen = line.x;
+    let mut i = 0;
+
+    while i < line.glyphs.len() {
+        let cluster = line.glyphs[i].cluster;
+        let rtl = line
+            .runs
+            .iter()
+            .find(|run| run.glyphs.contains(&i))
+            .is_some_and(|run| run.rtl);
+
+        let mut advance = 0.0;
+        while line.glyphs.get(i).is_some_and(|g| g.cluster == cluster) {
+            advance += line.glyphs[i].advance;
+            i += 1;
+        }
+
+        let end = match starts.binary_search(&cluster) {
+            Ok(k) => starts.get(k + 1).copied().unwrap_or(line.text.len()),
+            Err(_) => line.text.len(),
+        };
+
+        let graphemes = line.text[cluster..end].grapheme_indices(true);
+        let share = advance / graphemes.clone().count().max(1) as f32;
+
+        for (k, (offset, grapheme)) in graphemes.enumerate() {
+            let x0 = match rtl {
+                true => pen + advance - (k + 1) as f32 * share,
+                false => pen + k as f32 * share,
+            };
+
+            boxes.push(GraphemeBox {
+                start: cluster + offset,
+                end: cluster + offset + grapheme.len(),
+                x0,
+                x1: x0 + share,
+                rtl,
+            });
+        }
+
+        pen += advance;
+    }
+
+    boxes.sort_by(|a, b| a.x0.total_cmp(&b.x0));
+    boxes
+}
+
+impl TextLayout<'_> {
+    // the top and bottom of the caret and selection boxes of a line
+    fn line_span(&self, line: &LayoutLine) -> (f32, f32) {
+        let v_metrics = self.font.main.v_metrics(self.scale);
+        (
+            line.baseline - v_metrics.ascent,
+            line.baseline - v_metrics.descent,
+        )
+    }
+
+    /// Returns the caret position closest to `x`, `y` (relative to the top left corner of
+    /// the layout).
+    pub fn hit_test(&self, x: f32, y: f32) -> TextPosition {
+        if self.lines.is_empty() {
+            return TextPosition::default();
+        }
+
+        // lines don't have to be evenly spaced (flowed text skips the bands it doesn't fit
+        // in), between two lines the closest one is hit
+        let bottom = |line: &LayoutLine| line.top + self.line_height;
+        let line = match self.lines.iter().position(|l| y < bottom(l)) {
+            Some(0) => 0,
+            Some(i) if y - bottom(&self.lines[i - 1]) < self.lines[i].top - y => i - 1,
+            Some(i) => i,
+            None => self.lines.len() - 1,
+        };
+
+        let boxes = grapheme_boxes(&self.lines[line]);
+        let hit = boxes.iter().find(|b| x < b.x1).or_else(|| boxes.last());
+
+        let index = match hit {
+            Some(b) => {
+                let before = x < (b.x0 + b.x1) / 2.0;
+                if before != b.rtl {
+                    b.start
+                } else {
+                    b.end
+                }
+            }
+            None => 0,
+        };
+
+        TextPosition { line, index }
+    }
+
+    /// Returns the caret rectangle (one pixel wide, from the ascent to the descent of the
+    /// line) at `position`.
+    pub fn caret_rect(&self, position: TextPosition) -> Option<rusttype::Rect<f32>> {
+        let line = self.lines.get(position.line)?;
+        let boxes = grapheme_boxes(line);
+
+        let x = boxes
+            .iter()
+            .find(|b| b.start <= position.index && position.index < b.end)
+            .map(|b| b.leading())
+            .or_else(|| {
+                boxes
+                    .iter()
+                    .find(|b| b.end == position.index)
+                    .map(|b| b.trailing())
+            })
+            .unwrap_or(match line.rtl {
+                true => line.x + line.advance,
+                false => line.x,
+            });
+
+        let (top, bottom) = self.line_span(line);
+        Some(rusttype::Rect {
+            min: rusttype::point(x - 0.5, top),
+            max: rusttype::point(x + 0.5, bottom),
+        })
+    }
+
+    /// Returns the rectangles covering the text between `start` and `end`, one or more per
+    /// line (bidirectional text can split the selection of a line).
+    pub fn selection_rects(
+        &self,
+        start: TextPosition,
+        end: TextPosition,
+    ) -> Vec<rusttype::Rect<f32>> {
+        let (start, end) = (start.min(end), start.max(end));
+        let mut rects = Vec::new();
+
+        for (l, line) in self.lines.iter().enumerate() {
+            if l < start.line || l > end.line {
+                continue;
+            }
+
+            let lo = if l == start.line { start.index } else { 0 };
+            let hi = if l == end.line {
+                end.index
+            } else {
+                line.text.len()
+            };
+
+            let (top, bottom) = self.line_span(line);
+            let mut spans: Vec<(f32, f32)> = Vec::new();
+
+            for b in grapheme_boxes(line) {
+                if b.start >= hi || b.end <= lo {
+                    continue;
+                }
+
+                match spans.last_mut() {
+                    Some(span) if (b.x0 - span.1).abs() < 0.01 => span.1 = b.x1,
+                    _ => spans.push((b.x0, b.x1)),
+                }
+            }
+
+            rects.extend(spans.into_iter().map(|(x0, x1)| rusttype::Rect {
+                min: rusttype::point(x0, top),
+                max: rusttype::point(x1, bottom),
+            }));
+        }
+
+        rects
+    }
+}
+
+// `text` wrapped and laid out like `draw_text_wrapped` does, with the byte offsets of every
+// line mapped back to `text` (wrapping can collapse and trim whitespace)
+struct WrappedText<'font> {
+    layout: TextLayout<'font>,
+    offsets: Vec<Vec<usize>>,
+    origin: (f32, f32),
+}
+
+impl<'font> WrappedText<'font> {
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        x: f32,
+        y: f32,
+        ax: f32,
+        ay: f32,
+        width: f32,
+        scale: rusttype::Scale,
+        font: &SuperFont<'font>,
+        text: &str,
+        line_spacing: f32,
+        align: TextAlign,
+        wrap_style: WrapStyle,
+        options: &LayoutOptions,
+    ) -> Self {
+        let layout = TextLayout::wrapped(
+            text,
+            width,
+            font,
+            scale,
+            line_spacing,
+            align,
+            wrap_style,
+            options,
+        );
+        let origin = (x - width * ax, y - layout.height * ay);
+
+        let preserve = matches!(options.white_space, WhiteSpace::Pre | WhiteSpace::PreWrap);
+        let offsets = source_offsets(text, layout.lines.iter().map(|l| l.text.as_str()), preserve);
+
+        Self {
+            layout,
+            offsets,
+            origin,
+        }
+    }
+
+    fn to_source(&self, position: TextPosition) -> usize {
+        self.offsets
+            .get(position.line)
+            .and_then(|map| map.get(position.index))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    fn to_position(&self, index: usize) -> TextPosition {
+        let line = self
+            .offsets
+            .iter()
+            .rposition(|map| map[0] <= index)
+            .unwrap_or(0);
+
+        let index = self.layout.lines.get(line).map_or(0, |l| {
+            l.text
+                .grapheme_indices(true)
+                .map(|(i, _)| i)
+                .chain(std::iter::once(l.text.len()))
+                .take_while(|&i| self.offsets[line][i] <= index)
+                .last()
+                .unwrap_or(0)
+        });
+
+        TextPosition { line, index }
+    }
+}
+
+// the byte offsets in `text` of every byte of `lines` (and of their ends), `lines` being
+// `text` wrapped (wrapping can collapse and trim whitespace)
+pub(crate) fn source_offsets<'a>(
+    text: &str,
+    lines: impl Iterator<Item = &'a str>,
+    preserve: bool,
+) -> Vec<Vec<usize>> {
+    let mut source = text.char_indices().peekable();
+    let offset = |source: &mut std::iter::Peekable<std::str::CharIndices>| {
+        source.peek().map_or(text.len(), |(i, _)| *i)
+    };
+
+    lines
+        .enumerate()
+        .map(|(l, line)| {
+            let mut map = vec![0; line.len() + 1];
+
+            if preserve {
+                // skip the spaces removed at the end of the line before and its line
+                // break, but not the indentation of this line
+                let indent = line.chars().take_while(|c| c.is_whitespace()).count();
+                let run = source
+                    .clone()
+                    .take_while(|(_, c)| c.is_whitespace())
+                    .map(|(_, c)| c)
+                    .collect::<Vec<_>>();
+
+                let skip = match run.iter().position(|c| *c == '\n') {
+                    Some(n) if l > 0 => n + 1,
+                    _ => run.len().saturating_sub(indent),
+                };
+                for _ in 0..skip {
+                    source.next();
+                }
+            }
+
+            for (i, c) in line.char_indices() {
+                if c.is_whitespace() {
+                    map[i] = offset(&mut source);
+
+                    if preserve {
+                        source.next_if(|(_, c)| c.is_whitespace());
+                    } else {
+                        // a collapsed run of whitespace, or a space put between the
+                        // pieces of a word broken by characters
+                        while source.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+                    }
+                } else {
+                    // soft hyphens aren't drawn, except as the hyphen of a broken word
+                    while source
+                        .next_if(|(_, s)| s.is_whitespace() || (*s == SOFT_HYPHEN && c != '-'))
+                        .is_some()
+                    {}
+                    map[i] = offset(&mut source);
+
+                    // a hyphen put at a break has no character in the text
+                    source.next_if(|(_, s)| *s == c || *s == SOFT_HYPHEN);
+                }
+            }
+
+            map[line.len()] = offset(&mut source);
+            map
+        })
+        .collect()
+}
+
+/// Returns the byte offset in `text` of the caret position closest to the point `px`,
+/// `py`, with `text` laid out the same way as `draw_text_wrapped` with the same arguments
+/// and `options`.
+#[allow(clippy::too_many_arguments)]
+pub fn text_wrapped_index_at(
+    px: f32,
+    py: f32,
+    x: f32,
+    y: f32,
+    ax: f32,
+    ay: f32,
+    width: f32,
+    scale: rusttype::Scale,
+    font: &SuperFont,
+    text: &str,
+    line_spacing: f32,
+    align: TextAlign,
+    wrap_style: WrapStyle,
+    options: &LayoutOptions,
+) -> usize {
+    let wrapped = WrappedText::new(
+        x,
+        y,
+        ax,
+        ay,
+        width,
+        scale,
+        font,
+        text,
+        line_spacing,
+        align,
+        wrap_style,
+        options,
+    );
+
+    let position = wrapped
+        .layout
+        .hit_test(px - wrapped.origin.0, py - wrapped.origin.1);
+
+    wrapped.to_source(position)
+}
+
+/// Returns the caret rectangle at the byte offset `index` of `text`, laid out the same way
+/// as `draw_text_wrapped` with the same arguments and `options`.
+#[allow(clippy::too_many_arguments)]
+pub fn text_wrapped_caret_rect(
+    index: usize,
+    x: f32,
+    y: f32,
+    ax: f32,
+    ay: f32,
+    width: f32,
+    scale: rusttype::Scale,
+    font: &SuperFont,
+    text: &str,
+    line_spacing: f32,
+    align: TextAlign,
+    wrap_style: WrapStyle,
+    options: &LayoutOptions,
+) -> Option<rusttype::Rect<f32>> {
+    let wrapped = WrappedText::new(
+        x,
+        y,
+        ax,
+        ay,
+        width,
+        scale,
+        font,
+        text,
+        line_spacing,
+        align,
+        wrap_style,
+        options,
+    );
+
+    let rect = wrapped.layout.caret_rect(wrapped.to_position(index))?;
+    Some(offset_rect(rect, wrapped.origin))
+}
+
+/// Returns the selection rectangles of the byte range `range` of `text`, laid out the same
+/// way as `draw_text_wrapped` with the same arguments and `options`.
+#[allow(clippy::too_many_arguments)]
+pub fn text_wrapped_selection_rects(
+    range: std::ops::Range<usize>,
+    x: f32,
+    y: f32,
+    ax: f32,
+    ay: f32,
+    width: f32,
+    scale: rusttype::Scale,
+    font: &SuperFont,
+    text: &str,
+    line_spacing: f32,
+    align: TextAlign,
+    wrap_style: WrapStyle,
+    options: &LayoutOptions,
+) -> Vec<rusttype::Rect<f32>> {
+    let wrapped = WrappedText::new(
+        x,
+        y,
+        ax,
+        ay,
+        width,
+        scale,
+        font,
+        text,
+        line_spacing,
+        align,
+        wrap_style,
+        options,
+    );
+
+    wrapped
+        .layout
+        .selection_rects(
+            wrapped.to_position(range.start),
+            wrapped.to_position(range.end),
+        )
+        .into_iter()
+        .map(|rect| offset_rect(rect, wrapped.origin))
+        .collect()
+}
+
+#[inline]
+fn offset_rect(rect: rusttype::Rect<f32>, (x, y): (f32, f32)) -> rusttype::Rect<f32> {
+    rusttype::Rect {
+        min: rusttype::point(rect.min.x + x, rect.min.y + y),
+        max: rusttype::point(rect.max.x + x, rect.max.y + y),
+    }
+}
+
+#[cfg(feature = "emoji")]
+#[test]
+fn hit_test_test() {
+    let font = SuperFont::new(crate::emoji::EMOJI_FONT.clone(), vec![]);
+    let scale = rusttype::Scale::uniform(20.0);
+
+    let text = "11  22 33";
+    let args = (0.0, 0.0, 0.0, 0.0, 120.0, scale, &font, text, 1.0);
+
+    let index_at = |px, py| {
+        let (x, y, ax, ay, width, scale, font, text, line_spacing) = args;
+        text_wrapped_index_at(
+            px,
+            py,
+            x,
+            y,
+            ax,
+            ay,
+            width,
+            scale,
+            font,
+            text,
+            line_spacing,
+            TextAlign::Left,
+            WrapStyle::Word,
+            &LayoutOptions::default(),
+        )
+    };
+
+    // "11 22" fits on the first line, "33" goes to the second one
+    assert_eq!(index_at(-5.0, 5.0), 0);
+    assert_eq!(index_at(1.0, 5.0), 0);
+    assert_eq!(index_at(1000.0, 5.0), 6);
+    assert_eq!(index_at(-5.0, 25.0), 7);
+    assert_eq!(index_at(1000.0, 1000.0), 9);
+
+    let (x, y, ax, ay, width, scale, font, text, line_spacing) = args;
+    let caret = |index| {
+        text_wrapped_caret_rect(
+            index,
+            x,
+            y,
+            ax,
+            ay,
+            width,
+            scale,
+            font,
+            text,
+            line_spacing,
+            TextAlign::Left,
+            WrapStyle::Word,
+            &LayoutOptions::default(),
+        )
+        .unwrap()
+    };
+
+    assert_eq!(caret(0).min.x, -0.5);
+    assert!(caret(7).min.y > caret(0).min.y);
+    assert_eq!(caret(7).min.x, -0.5);
+    assert!(caret(4).min.x > caret(2).min.x);
+
+    let rects = text_wrapped_selection_rects(
+        1..8,
+        x,
+        y,
+        ax,
+        ay,
+        width,
+        scale,
+        font,
+        text,
+        line_spacing,
+        TextAlign::Left,
+        WrapStyle::Word,
+        &LayoutOptions::default(),
+    );
+
+    assert_eq!(rects.len(), 2);
+    assert!(rects[0].min.x > 0.0);
+    assert_eq!(rects[1].min.x, 0.0);
+}
+
+#[cfg(feature = "emoji")]
+#[test]
+fn flowed_hit_test() {
+    use crate::flow::FlowShape;
+
+    let font = SuperFont::new(crate::emoji::EMOJI_FONT.clone(), vec![]);
+    let scale = rusttype::Scale::uniform(20.0);
+    let width = crate::measure::text_width(scale, &font, "11 22") as f32;
+
+    // the second band is too narrow for a word, the second line goes below it
+    let shape = FlowShape::column(width)
+        .exclude(tiny_skia::Rect::from_xywh(5.0, 20.0, width, 20.0).unwrap());
+    let layout = TextLayout::flowed(
+        "11 22 11 22 11 22",
+        &shape,
+        &font,
+        scale,
+        1.0,
+        TextAlign::Left,
+        WrapStyle::Word,
+        &LayoutOptions::default(),
+    );
+    let tops = layout.lines.iter().map(|l| l.top).collect::<Vec<_>>();
+    assert_eq!(tops, vec![0.0, 40.0, 60.0]);
+
+    let line_at = |y| layout.hit_test(0.0, y).line;
+    assert_eq!(line_at(-10.0), 0);
+    assert_eq!(line_at(10.0), 0);
+    assert_eq!(line_at(25.0), 0);
+    assert_eq!(line_at(35.0), 1);
+    assert_eq!(line_at(45.0), 1);
+    assert_eq!(line_at(65.0), 2);
+    assert_eq!(line_at(1000.0), 2);
+}
@@ -0,0 +1,141 @@
+/// The languages hyphenation patterns are bundled for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    English,
+    French,
+    German,
+    Italian,
+    Portuguese,
+    Spanish,
+}
+
+impl Language {
+    /// Returns the language for an ISO 639-1 code (`"en"`, `"de"`...).
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code.to_ascii_lowercase().as_str() {
+            "en" => Some(Language::English),
+            "fr" => Some(Language::French),
+            "de" => Some(Language::German),
+            "it" => Some(Language::Italian),
+            "pt" => Some(Language::Portuguese),
+            "es" => Some(Language::Spanish),
+            _ => None,
+        }
+    }
+
+    fn patterns(self) -> hypher::Lang {
+        match self {
+            Language::English => hypher::Lang::English,
+            Language::French => hypher::Lang::French,
+            Language::German => hypher::Lang::German,
+            Language::Italian => hypher::Lang::Italian,
+            Language::Portuguese => hypher::Lang::Portuguese,
+            Language::Spanish => hypher::Lang::Spanish,
+        }
+    }
+}
+
+pub(crate) const SOFT_HYPHEN: char = '\u{ad}';
+
+/// A place a word can be broken at, the first part is `word[..end]` (followed by a hyphen
+/// if `hyphen` is set) and the rest `word[rest..]`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Break {
+    pub end: usize,
+    pub rest: usize,
+    pub hyphen: bool,
+}
+
+/// Returns the places `word` can be hyphenated at, in order: after hard hyphens, at soft
+/// hyphens and, when the word has no soft hyphens, at the syllables found by the patterns
+/// of `language`.
+pub(crate) fn breaks(word: &str, language: Option<Language>) -> Vec<Break> {
+    let mut breaks = Vec::new();
+    let has_soft_hyphens = word.contains(SOFT_HYPHEN);
+
+    let mut letters: Option<usize> = None;
+    let mut chars = word.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        let next = chars.peek().map_or(word.len(), |(i, _)| *i);
+
+        match c {
+            SOFT_HYPHEN if i > 0 && next < word.len() => breaks.push(Break {
+                end: i,
+                rest: next,
+                hyphen: true,
+            }),
+            '-' | '\u{2010}' if i > 0 && next < word.len() => breaks.push(Break {
+                end: next,
+                rest: next,
+                hyphen: false,
+            }),
+            _ => {}
+        }
+
+        if has_soft_hyphens {
+            continue;
+        }
+
+        let Some(language) = language else {
+            continue;
+        };
+
+        // hyphenate every run of letters on its own, so punctuation stays in place
+        match (c.is_alphabetic(), letters) {
+            (true, None) => letters = Some(i),
+            (false, Some(start)) => {
+                syllables(word, start, i, language, &mut breaks);
+                letters = None;
+            }
+            _ => {}
+        }
+
+        if next == word.len() {
+            if let Some(start) = letters {
+                syllables(word, start, next, language, &mut breaks);
+            }
+        }
+    }
+
+    breaks.sort_by_key(|b| b.end);
+    breaks
+}
+
+fn syllables(word: &str, start: usize, end: usize, language: Language, breaks: &mut Vec<Break>) {
+    let mut offset = start;
+    let syllables = hypher::hyphenate(&word[start..end], language.patterns());
+    let count = syllables.len();
+
+    for syllable in syllables.take(count.saturating_sub(1)) {
+        offset += syllable.len();
+        breaks.push(Break {
+            end: offset,
+            rest: offset,
+            hyphen: true,
+        });
+    }
+}
+
+#[test]
+fn hyphenation_breaks_test() {
+    fn parts(word: &str, language: Option<Language>) -> Vec<&str> {
+        breaks(word, language)
+            .into_iter()
+            .map(|b| &word[..b.end])
+            .collect()
+    }
+
+    assert_eq!(
+        parts("hyphenation,", Some(Language::English)),
+        vec!["hy", "hyphen"]
+    );
+    assert_eq!(parts("hyphenation", None), Vec::<&str>::new());
+
+    // soft hyphens take precedence over the patterns
+    assert_eq!(
+        parts("hyphen\u{ad}ation", Some(Language::English)),
+        vec!["hyphen"]
+    );
+    assert_eq!(parts("well-known", Some(Language::English)), vec!["well-"]);
+}
@@ -1,4 +1,12 @@
+use crate::flow::{text_flow, FlowLine, FlowShape};
+use crate::hit_test::source_offsets;
+use crate::measure::TextMetrics;
+use crate::outliner::{glyph_contours, AnchorMode, TextAlign, TextDecoration};
 use crate::prelude::SuperFont;
+use crate::shaping::{base_direction_rtl, shape, shape_vertical, ShapedGlyph};
+use crate::tabs::TabStops;
+use crate::wrap::{text_wrap_with_options, WhiteSpace, WrapStyle};
+use unicode_bidi::Level;
 
 impl<'font> SuperFont<'font> {
     pub fn layout<'a, 's>(
@@ -8,12 +16,28 @@ impl<'font> SuperFont<'font> {
         start: rusttype::Point<f32>,
     ) -> LayoutIter<'a, 'font, 's> {
         LayoutIter {
-            font: self,
-            scale,
+            glyphs: shape(self, text, scale, None, None, &TabStops::default()).into_iter(),
             start,
             caret: 0.0,
-            last_glyph: None,
-            chars: text.chars(),
+            _text: std::marker::PhantomData,
+        }
+    }
+
+    /// Lays `text` out top-to-bottom as a single column, `start` is the top center of the
+    /// column. Upright glyphs advance by the vertical metrics of their font, sideways
+    /// glyphs (latin, digits...) are rotated 90° clockwise around the column center.
+    /// Tabs aren't moved to tab stops, they advance like any other sideways glyph.
+    pub fn layout_vertical<'a, 's>(
+        &'a self,
+        text: &'s str,
+        scale: rusttype::Scale,
+        start: rusttype::Point<f32>,
+    ) -> VerticalLayoutIter<'a, 'font, 's> {
+        VerticalLayoutIter {
+            glyphs: shape_vertical(self, text, scale).into_iter(),
+            start,
+            caret: 0.0,
+            _text: std::marker::PhantomData,
         }
     }
 
@@ -26,98 +50,154 @@ impl<'font> SuperFont<'font> {
         scale: rusttype::Scale,
         start: rusttype::Point<f32>,
     ) -> LayoutWithEmojisIter<'a, 'font, 's> {
+        let emoji_scale = rusttype::Scale {
+            x: self.emoji_options.scale * scale.x,
+            y: self.emoji_options.scale * scale.y,
+        };
+
         LayoutWithEmojisIter {
-            font: self,
-            scale,
-            emoji_scale: rusttype::Scale {
-                x: self.emoji_options.scale * scale.x,
-                y: self.emoji_options.scale * scale.y,
-            },
+            glyphs: shape(
+                self,
+                text,
+                scale,
+                Some(emoji_scale),
+                None,
+                &TabStops::default(),
+            )
+            .into_iter(),
             start,
             caret: 0.0,
-            last_glyph: None,
-            chars: text.chars(),
+            placeholders: text
+                .match_indices(crate::emoji::parse::PLACEHOLDER_EMOJI)
+                .map(|(i, _)| i)
+                .collect(),
+            emoji_base: *emoji_idx,
             emojis,
             emoji_idx,
         }
     }
 }
 
+#[inline]
+fn position<'font>(
+    g: &ShapedGlyph<'_, 'font>,
+    caret: &mut f32,
+    start: rusttype::Point<f32>,
+) -> rusttype::PositionedGlyph<'font> {
+    let positioned = g
+        .font
+        .glyph(g.id)
+        .scaled(g.scale)
+        .positioned(rusttype::point(
+            start.x + *caret + g.x_offset,
+            start.y - g.y_offset,
+        ));
+
+    *caret += g.x_advance;
+    positioned
+}
+
 pub struct LayoutIter<'iter, 'font, 'text> {
-    font: &'iter SuperFont<'font>,
-    chars: core::str::Chars<'text>,
+    glyphs: std::vec::IntoIter<ShapedGlyph<'iter, 'font>>,
 
     caret: f32,
-    scale: rusttype::Scale,
     start: rusttype::Point<f32>,
 
-    last_glyph: Option<rusttype::GlyphId>,
+    _text: std::marker::PhantomData<&'text str>,
 }
 
 impl<'iter, 'font, 'text> Iterator for LayoutIter<'iter, 'font, 'text> {
     type Item = rusttype::PositionedGlyph<'font>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if let Some(c) = self.chars.next() {
-            let g = self.font.main.glyph(c);
-            let id = g.id();
-
-            if id.0 == 0 {
-                for font in self.font.fallbacks.iter() {
-                    let g = font.glyph(c);
-                    let id = g.id();
+        let g = self.glyphs.next()?;
+        Some(position(&g, &mut self.caret, self.start))
+    }
+}
 
-                    if id.0 == 0 {
-                        continue;
-                    }
+/// A glyph laid out in a vertical column.
+#[derive(Clone)]
+pub struct VerticalGlyph<'font> {
+    pub glyph: rusttype::PositionedGlyph<'font>,
+    /// rotated 90° clockwise around the glyph position
+    pub sideways: bool,
+}
 
-                    let g = g.scaled(self.scale);
+impl VerticalGlyph<'_> {
+    /// The pixel bounding box of the glyph as it is drawn, taking the rotation into
+    /// account.
+    pub fn pixel_bounding_box(&self) -> Option<rusttype::Rect<i32>> {
+        if !self.sideways {
+            return self.glyph.pixel_bounding_box();
+        }
 
-                    if let Some(last) = self.last_glyph {
-                        self.caret += font.pair_kerning(self.scale, last, id);
-                    }
+        let bb = self.glyph.unpositioned().exact_bounding_box()?;
+        let p = self.glyph.position();
 
-                    let advance_width = g.h_metrics().advance_width;
-                    let g = g.positioned(rusttype::point(self.caret + self.start.x, self.start.y));
+        // (x, y) -> (-y, x)
+        Some(rusttype::Rect {
+            min: rusttype::point(
+                (p.x - bb.max.y).floor() as i32,
+                (p.y + bb.min.x).floor() as i32,
+            ),
+            max: rusttype::point(
+                (p.x - bb.min.y).ceil() as i32,
+                (p.y + bb.max.x).ceil() as i32,
+            ),
+        })
+    }
+}
 
-                    self.caret += advance_width;
-                    self.last_glyph = Some(id);
+pub struct VerticalLayoutIter<'iter, 'font, 'text> {
+    glyphs: std::vec::IntoIter<ShapedGlyph<'iter, 'font>>,
 
-                    return Some(g);
-                }
-            }
-            let g = g.scaled(self.scale);
+    caret: f32,
+    start: rusttype::Point<f32>,
 
-            if let Some(last) = self.last_glyph {
-                self.caret += self.font.main.pair_kerning(self.scale, last, id);
-            }
+    _text: std::marker::PhantomData<&'text str>,
+}
 
-            let advance_width = g.h_metrics().advance_width;
+impl<'iter, 'font, 'text> Iterator for VerticalLayoutIter<'iter, 'font, 'text> {
+    type Item = VerticalGlyph<'font>;
 
-            let g = g.positioned(rusttype::point(self.caret + self.start.x, self.start.y));
+    fn next(&mut self) -> Option<Self::Item> {
+        let g = self.glyphs.next()?;
 
-            self.caret += advance_width;
-            self.last_glyph = Some(id);
+        let position = if g.sideways {
+            // center the em box of the rotated run on the column
+            let v_metrics = g.font.v_metrics(g.scale);
+            let baseline = self.start.x - (v_metrics.ascent + v_metrics.descent) / 2.0;
 
-            Some(g)
+            rusttype::point(
+                baseline + g.y_offset,
+                self.start.y + self.caret + g.x_offset,
+            )
         } else {
-            None
-        }
+            rusttype::point(
+                self.start.x + g.x_offset,
+                self.start.y + self.caret - g.y_offset,
+            )
+        };
+
+        self.caret += g.y_advance;
+
+        Some(VerticalGlyph {
+            glyph: g.font.glyph(g.id).scaled(g.scale).positioned(position),
+            sideways: g.sideways,
+        })
     }
 }
 
 #[cfg(feature = "emoji")]
 pub struct LayoutWithEmojisIter<'iter, 'font, 'text> {
-    font: &'iter SuperFont<'font>,
-    chars: core::str::Chars<'text>,
+    glyphs: std::vec::IntoIter<ShapedGlyph<'iter, 'font>>,
 
     caret: f32,
-    scale: rusttype::Scale,
-    emoji_scale: rusttype::Scale,
-
     start: rusttype::Point<f32>,
 
-    last_glyph: Option<rusttype::GlyphId>,
+    // byte offsets of the emoji placeholders, used to map glyphs back to emojis
+    placeholders: Vec<usize>,
+    emoji_base: usize,
 
     emojis: &'text [crate::emoji::source::EmojiType],
     emoji_idx: &'text mut usize,
@@ -131,72 +211,1538 @@ impl<'iter, 'font, 'text> Iterator for LayoutWithEmojisIter<'iter, 'font, 'text>
     );
 
     fn next(&mut self) -> Option<Self::Item> {
-        if let Some(c) = self.chars.next() {
-            if c == crate::emoji::parse::PLACEHOLDER_EMOJI {
-                let g = crate::emoji::EMOJI_FONT.glyph(crate::emoji::parse::PLACEHOLDER_EMOJI);
-                let id = g.id();
+        let g = self.glyphs.next()?;
+        let positioned = position(&g, &mut self.caret, self.start);
 
-                let g = g.scaled(self.emoji_scale);
+        if g.font_index != crate::shaping::EMOJI_FONT_INDEX {
+            return Some((positioned, None));
+        }
 
-                if let Some(last) = self.last_glyph {
-                    self.caret += crate::emoji::EMOJI_FONT.pair_kerning(self.scale, last, id);
-                }
+        let emoji = match self.placeholders.binary_search(&g.cluster) {
+            Ok(ordinal) => {
+                *self.emoji_idx += 1;
+                self.emojis.get(self.emoji_base + ordinal)
+            }
+            Err(_) => None,
+        };
 
-                let advance_width = g.h_metrics().advance_width;
-                let g = g.positioned(rusttype::point(self.caret + self.start.x, self.start.y));
+        Some((positioned, emoji))
+    }
+}
 
-                self.caret += advance_width;
-                self.last_glyph = Some(id);
+/// A glyph of a [`TextLayout`], positioned relative to the top left corner of the layout.
+#[derive(Clone)]
+pub struct LayoutGlyph<'font> {
+    pub glyph: rusttype::PositionedGlyph<'font>,
+    /// byte offset of the cluster the glyph belongs to in the text of its line
+    pub cluster: usize,
+    /// horizontal advance in pixels, including letter and word spacing
+    pub advance: f32,
 
-                let emoji = self.emojis.get(*self.emoji_idx);
-                *self.emoji_idx += 1;
+    /// the emoji drawn in place of this glyph
+    #[cfg(feature = "emoji")]
+    pub emoji: Option<crate::emoji::source::EmojiType>,
+}
+
+/// Consecutive glyphs of a line that share a font and a direction.
+#[derive(Clone)]
+pub struct GlyphRun<'font> {
+    pub font: rusttype::Font<'font>,
+    /// range of the run in [`LayoutLine::glyphs`]
+    pub glyphs: std::ops::Range<usize>,
+    pub rtl: bool,
+}
+
+#[derive(Clone)]
+pub struct LayoutLine<'font> {
+    /// the text of the line (with emojis replaced by placeholders when they were parsed)
+    pub text: String,
+    /// glyphs in visual (left-to-right) order
+    pub glyphs: Vec<LayoutGlyph<'font>>,
+    pub runs: Vec<GlyphRun<'font>>,
+
+    /// left edge of the line in the layout, after alignment
+    pub x: f32,
+    pub top: f32,
+    pub baseline: f32,
+    /// width of the line in pixels (up to the right edge of its last glyph)
+    pub width: f32,
+    /// sum of the advances of the glyphs of the line
+    pub advance: f32,
+    /// the base direction of the line
+    pub rtl: bool,
+}
+
+impl<'font> LayoutLine<'font> {
+    /// Widens the gaps between words, and after CJK characters when `cjk` is set, so that
+    /// the line is `extra` pixels wider. Lines without any gap are left as is.
+    fn justify(&mut self, extra: f32, cjk: bool) {
+        if extra <= 0.0 {
+            return;
+        }
+
+        // text before a tab stays at its tab stop
+        let start = self
+            .glyphs
+            .iter()
+            .rposition(|g| self.text[g.cluster..].starts_with('\t'))
+            .map_or(0, |i| i + 1);
+
+        // the last glyph of a cluster that is a word separator or an ideograph takes the
+        // extra space after it, unless it ends the line
+        let gaps = (0..self.glyphs.len())
+            .map(|i| {
+                let cluster = self.glyphs[i].cluster;
+                let stretches = i >= start
+                    && self.text[cluster..]
+                        .chars()
+                        .next()
+                        .is_some_and(|c| c.is_whitespace() || (cjk && is_cjk(c)));
+
+                stretches && self.glyphs.get(i + 1).is_some_and(|g| g.cluster != cluster)
+            })
+            .collect::<Vec<_>>();
+
+        let count = gaps.iter().filter(|gap| **gap).count();
+        if count == 0 {
+            return;
+        }
 
-                return Some((g, emoji));
+        let space = extra / count as f32;
+        let mut offset = 0.0;
+        for (g, gap) in self.glyphs.iter_mut().zip(gaps) {
+            if offset != 0.0 {
+                let p = g.glyph.position();
+                g.glyph.set_position(rusttype::point(p.x + offset, p.y));
             }
 
-            let g = self.font.main.glyph(c);
-            let id = g.id();
+            if gap {
+                g.advance += space;
+                offset += space;
+            }
+        }
+
+        self.width += extra;
+        self.advance += extra;
+    }
+}
 
-            if id.0 == 0 {
-                for font in self.font.fallbacks.iter() {
-                    let g = font.glyph(c);
-                    let id = g.id();
+// the parts of the span from `x0` to `x1` that the glyphs of `line` (but emojis) don't
+// cross between `top` and `bottom`, keeping `gap` pixels away from them
+fn skip_ink(
+    line: &LayoutLine,
+    x0: f32,
+    x1: f32,
+    top: f32,
+    bottom: f32,
+    gap: f32,
+) -> Vec<(f32, f32)> {
+    let (top, bottom) = (top - gap, bottom + gap);
 
-                    if id.0 == 0 {
+    let cuts = line.glyphs.iter().filter_map(|g| {
+        #[cfg(feature = "emoji")]
+        if g.emoji.is_some() {
+            return None;
+        }
+
+        let bb = g.glyph.pixel_bounding_box()?;
+        if bb.max.y as f32 <= top || bb.min.y as f32 >= bottom {
+            return None;
+        }
+
+        // the horizontal extent of the outline between `top` and `bottom`
+        let (mut min, mut max) = (f32::INFINITY, f32::NEG_INFINITY);
+        for contour in glyph_contours(&g.glyph) {
+            for (a, b) in contour.iter().zip(contour.iter().cycle().skip(1)) {
+                for t in [
+                    0.0,
+                    1.0,
+                    (top - a.y) / (b.y - a.y),
+                    (bottom - a.y) / (b.y - a.y),
+                ] {
+                    if !(0.0..=1.0).contains(&t) {
                         continue;
                     }
 
-                    let g = g.scaled(self.scale);
-
-                    if let Some(last) = self.last_glyph {
-                        self.caret += font.pair_kerning(self.scale, last, id);
+                    let (x, y) = (a.x + (b.x - a.x) * t, a.y + (b.y - a.y) * t);
+                    if y >= top - 0.01 && y <= bottom + 0.01 {
+                        min = min.min(x);
+                        max = max.max(x);
                     }
+                }
+            }
+        }
 
-                    let advance_width = g.h_metrics().advance_width;
-                    let g = g.positioned(rusttype::point(self.caret + self.start.x, self.start.y));
+        (min <= max).then_some((min - gap, max + gap))
+    });
 
-                    self.caret += advance_width;
-                    self.last_glyph = Some(id);
+    let mut spans = vec![(x0, x1)];
+    for (c0, c1) in cuts {
+        spans = spans
+            .into_iter()
+            .flat_map(|(x0, x1)| [(x0, x1.min(c0)), (x0.max(c1), x1)])
+            .filter(|(x0, x1)| x1 > x0)
+            .collect();
+    }
+    spans
+}
 
-                    return Some((g, None));
-                }
+/// The base direction (right to left when `true`) of the paragraph every line of `lines`,
+/// wrapped from `text` with `white_space`, starts in. Paragraphs are separated by line
+/// breaks, the ones without a strong character keep the direction of the paragraph before
+/// them.
+pub(crate) fn paragraph_directions<'a>(
+    text: &str,
+    lines: impl Iterator<Item = &'a String>,
+    white_space: WhiteSpace,
+) -> Vec<bool> {
+    let preserve = matches!(white_space, WhiteSpace::Pre | WhiteSpace::PreWrap);
+    let mut rtl = false;
+    // the end of the paragraph of the previous line
+    let mut end = None;
+
+    source_offsets(text, lines.map(String::as_str), preserve)
+        .iter()
+        .map(|map| {
+            let start = map[0];
+            if end.is_none_or(|end| start > end) {
+                let paragraph_start = text[..start].rfind('\n').map_or(0, |i| i + 1);
+                let paragraph_end = text[start..].find('\n').map_or(text.len(), |i| start + i);
+
+                rtl = base_direction_rtl(&text[paragraph_start..paragraph_end]).unwrap_or(rtl);
+                end = Some(paragraph_end);
             }
-            let g = g.scaled(self.scale);
+            rtl
+        })
+        .collect()
+}
+
+fn is_cjk(c: char) -> bool {
+    use unicode_script::{Script, UnicodeScript};
+
+    matches!(
+        c.script(),
+        Script::Han | Script::Hiragana | Script::Katakana | Script::Bopomofo
+    )
+}
+
+/// How text is laid out and drawn, apart from its fonts. Functions that don't take options
+/// use the defaults.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LayoutOptions {
+    /// how text is measured for anchoring and alignment
+    pub anchor_mode: AnchorMode,
+    /// how wrapping handles line breaks and runs of spaces in the text
+    pub white_space: WhiteSpace,
+    /// where tabs move the text after them
+    pub tab_stops: TabStops,
+    /// lines drawn under, through or over the text
+    pub decoration: TextDecoration,
+    /// whether justified lines also widen the gaps between CJK characters, not only the
+    /// ones between words
+    pub justify_cjk: bool,
+}
+
+impl LayoutOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-            if let Some(last) = self.last_glyph {
-                self.caret += self.font.main.pair_kerning(self.scale, last, id);
+    pub fn with_anchor_mode(mut self, anchor_mode: AnchorMode) -> Self {
+        self.anchor_mode = anchor_mode;
+        self
+    }
+
+    pub fn with_white_space(mut self, white_space: WhiteSpace) -> Self {
+        self.white_space = white_space;
+        self
+    }
+
+    /// Tabs only reach their stops in text that keeps them: wrapping with
+    /// `WhiteSpace::Normal` (the default) or `WhiteSpace::PreLine` collapses them into
+    /// spaces like any other whitespace, `Pre` and `PreWrap` keep them. Lines that aren't
+    /// wrapped (`TextLayout::new`, `TextLayout::multiline`) always keep them.
+    pub fn with_tab_stops(mut self, tab_stops: TabStops) -> Self {
+        self.tab_stops = tab_stops;
+        self
+    }
+
+    pub fn with_decoration(mut self, decoration: TextDecoration) -> Self {
+        self.decoration = decoration;
+        self
+    }
+
+    pub fn with_justify_cjk(mut self, justify_cjk: bool) -> Self {
+        self.justify_cjk = justify_cjk;
+        self
+    }
+}
+
+/// Text that was shaped and laid out once, which can be inspected (line widths, baselines,
+/// bounds, glyphs) and drawn any number of times with `TextDrawer::draw_layout` or
+/// `draw_text_layout`, without laying the text out again.
+///
+/// Positions are relative to the top left corner of the layout.
+#[derive(Clone)]
+pub struct TextLayout<'font> {
+    pub lines: Vec<LayoutLine<'font>>,
+
+    /// the width of the layout, used for anchoring
+    pub width: f32,
+    /// the height of the layout, used for anchoring
+    pub height: f32,
+
+    pub scale: rusttype::Scale,
+    pub line_height: f32,
+
+    pub(crate) font: SuperFont<'font>,
+    pub(crate) options: LayoutOptions,
+}
+
+impl<'font> TextLayout<'font> {
+    /// Lays `text` out as a single line, the size of the layout is the same as
+    /// `text_size`, or measured by the [`AnchorMode`] of `options`.
+    pub fn new(
+        text: &str,
+        font: &SuperFont<'font>,
+        scale: rusttype::Scale,
+        options: &LayoutOptions,
+    ) -> Self {
+        LayoutBuilder::new(font, scale, options).single(text)
+    }
+
+    /// Same as [`TextLayout::new`], with the base direction `rtl`.
+    pub(crate) fn directed(
+        text: &str,
+        rtl: bool,
+        font: &SuperFont<'font>,
+        scale: rusttype::Scale,
+        options: &LayoutOptions,
+    ) -> Self {
+        LayoutBuilder::new(font, scale, options)
+            .with_directions(vec![rtl])
+            .single(text)
+    }
+
+    /// Lays lines out top to bottom, `line_spacing` is a multiple of `scale.y`. Lines are
+    /// aligned within `width`, or within the widest line when it's `None`.
+    pub fn multiline(
+        lines: &[String],
+        font: &SuperFont<'font>,
+        scale: rusttype::Scale,
+        line_spacing: f32,
+        align: TextAlign,
+        width: Option<f32>,
+        options: &LayoutOptions,
+    ) -> Self {
+        LayoutBuilder::new(font, scale, options).multiline(lines, line_spacing, align, width)
+    }
+
+    /// Wraps `text` to `width` and lays the lines out aligned within `width`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn wrapped(
+        text: &str,
+        width: f32,
+        font: &SuperFont<'font>,
+        scale: rusttype::Scale,
+        line_spacing: f32,
+        align: TextAlign,
+        wrap_style: WrapStyle,
+        options: &LayoutOptions,
+    ) -> Self {
+        let lines = text_wrap_with_options(
+            text,
+            width as i32,
+            font,
+            scale,
+            wrap_style,
+            options,
+            |scale, font, text| TextLayout::ink_width(text, font, scale, options),
+        );
+
+        LayoutBuilder::new(font, scale, options)
+            .with_directions(paragraph_directions(
+                text,
+                lines.iter(),
+                options.white_space,
+            ))
+            .multiline(&lines, line_spacing, align, Some(width))
+    }
+
+    /// Flows `text` in `shape` and lays the lines out, each line is aligned in its span.
+    /// See [`text_flow`](crate::flow::text_flow).
+    #[allow(clippy::too_many_arguments)]
+    pub fn flowed(
+        text: &str,
+        shape: &FlowShape,
+        font: &SuperFont<'font>,
+        scale: rusttype::Scale,
+        line_spacing: f32,
+        align: TextAlign,
+        wrap_style: WrapStyle,
+        options: &LayoutOptions,
+    ) -> Self {
+        let lines = text_flow(
+            text,
+            shape,
+            font,
+            scale,
+            line_spacing,
+            wrap_style,
+            options,
+            |scale, font, text| TextLayout::ink_width(text, font, scale, options),
+        );
+
+        LayoutBuilder::new(font, scale, options)
+            .with_directions(paragraph_directions(
+                text,
+                lines.iter().map(|l| &l.text),
+                options.white_space,
+            ))
+            .flow(&lines, line_spacing, align)
+    }
+
+    /// Same as [`TextLayout::multiline`], every line with the base direction (right to
+    /// left when `true`) in `directions`.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn multiline_directed(
+        lines: &[String],
+        directions: Vec<bool>,
+        font: &SuperFont<'font>,
+        scale: rusttype::Scale,
+        line_spacing: f32,
+        align: TextAlign,
+        width: Option<f32>,
+        options: &LayoutOptions,
+    ) -> Self {
+        LayoutBuilder::new(font, scale, options)
+            .with_directions(directions)
+            .multiline(lines, line_spacing, align, width)
+    }
+
+    // the right edge of the ink of `text` laid out as a single line, as measured by
+    // `text_width`, without building a whole layout (it's the `width_fn` of wrapping)
+    pub(crate) fn ink_width(
+        text: &str,
+        font: &SuperFont<'font>,
+        scale: rusttype::Scale,
+        options: &LayoutOptions,
+    ) -> i32 {
+        LayoutBuilder::new(font, scale, options).ink_width(text)
+    }
+
+    /// Returns the size of the layout rounded to pixels, as returned by `text_size` and
+    /// `text_size_multiline`.
+    pub fn size(&self) -> (i32, i32) {
+        (self.width.round() as i32, self.height.round() as i32)
+    }
+
+    /// Returns the pixel bounds of all the glyphs of the layout.
+    pub fn bounds(&self) -> Option<rusttype::Rect<i32>> {
+        self.glyphs()
+            .filter_map(|g| g.glyph.pixel_bounding_box())
+            .reduce(|a, b| rusttype::Rect {
+                min: rusttype::point(a.min.x.min(b.min.x), a.min.y.min(b.min.y)),
+                max: rusttype::point(a.max.x.max(b.max.x), a.max.y.max(b.max.y)),
+            })
+    }
+
+    /// Returns the metrics of the layout, relative to the left edge of the layout on the
+    /// baseline of the first line.
+    pub fn metrics(&self) -> TextMetrics {
+        let v_metrics = self.font.main.v_metrics(self.scale);
+        let origin = self.lines.first().map_or(v_metrics.ascent, |l| l.baseline);
+
+        let union = |a: rusttype::Rect<f32>, b: rusttype::Rect<f32>| rusttype::Rect {
+            min: rusttype::point(a.min.x.min(b.min.x), a.min.y.min(b.min.y)),
+            max: rusttype::point(a.max.x.max(b.max.x), a.max.y.max(b.max.y)),
+        };
+
+        let logical_bounds = self
+            .lines
+            .iter()
+            .map(|line| rusttype::Rect {
+                min: rusttype::point(line.x, line.baseline - origin - v_metrics.ascent),
+                max: rusttype::point(
+                    line.x + line.advance,
+                    line.baseline - origin - v_metrics.descent,
+                ),
+            })
+            .reduce(union)
+            .unwrap_or(rusttype::Rect {
+                min: rusttype::point(0.0, -v_metrics.ascent),
+                max: rusttype::point(0.0, -v_metrics.descent),
+            });
+
+        let ink_bounds = self
+            .glyphs()
+            .filter_map(|g| {
+                let bb = g.glyph.unpositioned().exact_bounding_box()?;
+                let p = g.glyph.position();
+
+                Some(rusttype::Rect {
+                    min: rusttype::point(p.x + bb.min.x, p.y - origin + bb.min.y),
+                    max: rusttype::point(p.x + bb.max.x, p.y - origin + bb.max.y),
+                })
+            })
+            .reduce(union);
+
+        TextMetrics {
+            advance: self.lines.iter().fold(0.0, |a, l| l.advance.max(a)),
+            ascent: v_metrics.ascent,
+            descent: v_metrics.descent,
+            line_gap: v_metrics.line_gap,
+            ink_bounds,
+            logical_bounds,
+        }
+    }
+
+    pub fn glyphs(&self) -> impl Iterator<Item = &LayoutGlyph<'font>> {
+        self.lines.iter().flat_map(|line| line.glyphs.iter())
+    }
+
+    /// Returns the rectangles of the [`TextDecoration`] of the layout options, placed with
+    /// the underline, strikeout and ascender metrics of the main font and spanning the
+    /// advance of every line. Drawing a layout adds them to the path of its glyphs.
+    pub fn decorations(&self) -> Vec<rusttype::Rect<f32>> {
+        let decoration = self.options.decoration;
+        if decoration.is_none() {
+            return Vec::new();
+        }
+
+        let face = crate::shaping::face(&self.font.main);
+        let s = self.font.main.scale_for_pixel_height(self.scale.y);
+        let upem = face.units_per_em() as f32;
+
+        // metrics that put an underline above the baseline, or a strikeout below it, are
+        // broken and left out
+        let underline = face
+            .underline_metrics()
+            .filter(|m| m.position < 0 && m.thickness > 0)
+            .map_or((-upem / 10.0, upem / 14.0), |m| {
+                (m.position as f32, m.thickness as f32)
+            });
+        let strikeout = face
+            .strikeout_metrics()
+            .filter(|m| m.position > 0 && m.thickness > 0)
+            .map_or_else(
+                || {
+                    let x_height = face
+                        .x_height()
+                        .filter(|h| *h > 0)
+                        .map_or(face.ascender() as f32 / 2.0, |h| h as f32);
+                    (x_height / 2.0 + underline.1 / 2.0, underline.1)
+                },
+                |m| (m.position as f32, m.thickness as f32),
+            );
+        // the position is the top of the line, like for the other metrics
+        let overline = (face.ascender() as f32, underline.1);
+
+        // (position of the top above the baseline, thickness) in pixels, and whether
+        // glyphs cut it
+        let bands = [
+            (decoration.underline, underline, true),
+            (decoration.strikethrough, strikeout, false),
+            (decoration.overline, overline, true),
+        ]
+        .into_iter()
+        .filter(|(on, _, _)| *on)
+        .map(|(_, (position, thickness), cut)| {
+            (
+                position * s,
+                (thickness * s).max(1.0),
+                cut && decoration.skip_ink,
+            )
+        })
+        .collect::<Vec<_>>();
+
+        let mut rects = Vec::new();
+        for line in self.lines.iter().filter(|line| line.advance > 0.0) {
+            for (position, thickness, cut) in &bands {
+                let (top, bottom) = (
+                    line.baseline - position,
+                    line.baseline - position + thickness,
+                );
+
+                let spans = if *cut {
+                    skip_ink(line, line.x, line.x + line.advance, top, bottom, *thickness)
+                } else {
+                    vec![(line.x, line.x + line.advance)]
+                };
+
+                rects.extend(spans.into_iter().map(|(x0, x1)| rusttype::Rect {
+                    min: rusttype::point(x0, top),
+                    max: rusttype::point(x1, bottom),
+                }));
             }
+        }
 
-            let advance_width = g.h_metrics().advance_width;
+        rects
+    }
 
-            let g = g.positioned(rusttype::point(self.caret + self.start.x, self.start.y));
+    pub fn font(&self) -> &SuperFont<'font> {
+        &self.font
+    }
 
-            self.caret += advance_width;
-            self.last_glyph = Some(id);
+    pub fn options(&self) -> &LayoutOptions {
+        &self.options
+    }
+}
 
-            Some((g, None))
-        } else {
-            None
+#[cfg(feature = "emoji")]
+impl<'font> TextLayout<'font> {
+    // same as `ink_width`, parsing emojis out of `text` first
+    pub(crate) fn ink_width_with_emojis(
+        text: &str,
+        font: &SuperFont<'font>,
+        scale: rusttype::Scale,
+        options: &LayoutOptions,
+    ) -> i32 {
+        let (text, _) = crate::emoji::parse::parse_out_emojis(
+            text,
+            font.emoji_options.parse_shortcodes,
+            font.emoji_options.parse_discord_emojis,
+        );
+        Self::parsed_ink_width_with_emojis(&text, font, scale, options)
+    }
+
+    // same as `ink_width`, assumes that emojis were parsed out
+    pub(crate) fn parsed_ink_width_with_emojis(
+        text: &str,
+        font: &SuperFont<'font>,
+        scale: rusttype::Scale,
+        options: &LayoutOptions,
+    ) -> i32 {
+        LayoutBuilder::new(font, scale, options)
+            .with_emojis(&[], 0)
+            .ink_width(text)
+    }
+
+    /// Lays `text` out as a single line, parsing emojis out of it first.
+    pub fn new_with_emojis(
+        text: &str,
+        font: &SuperFont<'font>,
+        scale: rusttype::Scale,
+        options: &LayoutOptions,
+    ) -> Self {
+        let (text, emojis) = crate::emoji::parse::parse_out_emojis(
+            text,
+            font.emoji_options.parse_shortcodes,
+            font.emoji_options.parse_discord_emojis,
+        );
+
+        Self::parsed_with_emojis(&text, &emojis, &mut 0, font, scale, options)
+    }
+
+    /// Lays `text` out as a single line.
+    ///
+    /// assumes that emojis were parsed out, `emoji_idx` is the index of the first emoji of
+    /// `text` in `emojis` and is advanced past the emojis of the text
+    pub fn parsed_with_emojis(
+        text: &str,
+        emojis: &[crate::emoji::source::EmojiType],
+        emoji_idx: &mut usize,
+        font: &SuperFont<'font>,
+        scale: rusttype::Scale,
+        options: &LayoutOptions,
+    ) -> Self {
+        let mut builder = LayoutBuilder::new(font, scale, options).with_emojis(emojis, *emoji_idx);
+        let layout = builder.single(text);
+
+        *emoji_idx = builder.emoji_idx;
+        layout
+    }
+
+    /// Same as [`TextLayout::parsed_with_emojis`], with the base direction `rtl`.
+    pub(crate) fn parsed_directed_with_emojis(
+        text: &str,
+        rtl: bool,
+        emojis: &[crate::emoji::source::EmojiType],
+        emoji_idx: &mut usize,
+        font: &SuperFont<'font>,
+        scale: rusttype::Scale,
+        options: &LayoutOptions,
+    ) -> Self {
+        let mut builder = LayoutBuilder::new(font, scale, options)
+            .with_directions(vec![rtl])
+            .with_emojis(emojis, *emoji_idx);
+        let layout = builder.single(text);
+
+        *emoji_idx = builder.emoji_idx;
+        layout
+    }
+
+    /// Lays lines out top to bottom, parsing emojis out of every line first.
+    pub fn multiline_with_emojis(
+        lines: &[String],
+        font: &SuperFont<'font>,
+        scale: rusttype::Scale,
+        line_spacing: f32,
+        align: TextAlign,
+        width: Option<f32>,
+        options: &LayoutOptions,
+    ) -> Self {
+        Self::multiline_directed_with_emojis(
+            lines,
+            Vec::new(),
+            font,
+            scale,
+            line_spacing,
+            align,
+            width,
+            options,
+        )
+    }
+
+    /// Same as [`TextLayout::multiline_with_emojis`], every line with the base direction
+    /// in `directions`.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn multiline_directed_with_emojis(
+        lines: &[String],
+        directions: Vec<bool>,
+        font: &SuperFont<'font>,
+        scale: rusttype::Scale,
+        line_spacing: f32,
+        align: TextAlign,
+        width: Option<f32>,
+        options: &LayoutOptions,
+    ) -> Self {
+        let mut emojis = Vec::new();
+
+        let lines = lines
+            .iter()
+            .map(|l| {
+                let (text, line_emojis) = crate::emoji::parse::parse_out_emojis(
+                    l,
+                    font.emoji_options.parse_shortcodes,
+                    font.emoji_options.parse_discord_emojis,
+                );
+
+                emojis.extend(line_emojis);
+                text
+            })
+            .collect::<Vec<_>>();
+
+        LayoutBuilder::new(font, scale, options)
+            .with_directions(directions)
+            .with_emojis(&emojis, 0)
+            .multiline(&lines, line_spacing, align, width)
+    }
+
+    /// Lays lines out top to bottom.
+    ///
+    /// assumes that emojis were parsed out
+    #[allow(clippy::too_many_arguments)]
+    pub fn parsed_multiline_with_emojis(
+        lines: &[String],
+        emojis: &[crate::emoji::source::EmojiType],
+        emoji_idx: &mut usize,
+        font: &SuperFont<'font>,
+        scale: rusttype::Scale,
+        line_spacing: f32,
+        align: TextAlign,
+        width: Option<f32>,
+        options: &LayoutOptions,
+    ) -> Self {
+        let mut builder = LayoutBuilder::new(font, scale, options).with_emojis(emojis, *emoji_idx);
+        let layout = builder.multiline(lines, line_spacing, align, width);
+
+        *emoji_idx = builder.emoji_idx;
+        layout
+    }
+
+    /// Wraps `text` to `width` and lays the lines out, parsing emojis out of it first.
+    #[allow(clippy::too_many_arguments)]
+    pub fn wrapped_with_emojis(
+        text: &str,
+        width: f32,
+        font: &SuperFont<'font>,
+        scale: rusttype::Scale,
+        line_spacing: f32,
+        align: TextAlign,
+        wrap_style: WrapStyle,
+        options: &LayoutOptions,
+    ) -> Self {
+        let (text, emojis) = crate::emoji::parse::parse_out_emojis(
+            text,
+            font.emoji_options.parse_shortcodes,
+            font.emoji_options.parse_discord_emojis,
+        );
+
+        Self::parsed_wrapped_with_emojis(
+            &text,
+            &emojis,
+            &mut 0,
+            width,
+            font,
+            scale,
+            line_spacing,
+            align,
+            wrap_style,
+            options,
+        )
+    }
+
+    /// Wraps `text` to `width` and lays the lines out.
+    ///
+    /// assumes that emojis were parsed out
+    #[allow(clippy::too_many_arguments)]
+    pub fn parsed_wrapped_with_emojis(
+        text: &str,
+        emojis: &[crate::emoji::source::EmojiType],
+        emoji_idx: &mut usize,
+        width: f32,
+        font: &SuperFont<'font>,
+        scale: rusttype::Scale,
+        line_spacing: f32,
+        align: TextAlign,
+        wrap_style: WrapStyle,
+        options: &LayoutOptions,
+    ) -> Self {
+        let lines = text_wrap_with_options(
+            text,
+            width as i32,
+            font,
+            scale,
+            wrap_style,
+            options,
+            |scale, font, text| {
+                TextLayout::parsed_ink_width_with_emojis(text, font, scale, options)
+            },
+        );
+
+        let mut builder = LayoutBuilder::new(font, scale, options)
+            .with_directions(paragraph_directions(
+                text,
+                lines.iter(),
+                options.white_space,
+            ))
+            .with_emojis(emojis, *emoji_idx);
+        let layout = builder.multiline(&lines, line_spacing, align, Some(width));
+
+        *emoji_idx = builder.emoji_idx;
+        layout
+    }
+
+    /// Flows `text` in `shape` and lays the lines out, parsing emojis out of it first.
+    #[allow(clippy::too_many_arguments)]
+    pub fn flowed_with_emojis(
+        text: &str,
+        shape: &FlowShape,
+        font: &SuperFont<'font>,
+        scale: rusttype::Scale,
+        line_spacing: f32,
+        align: TextAlign,
+        wrap_style: WrapStyle,
+        options: &LayoutOptions,
+    ) -> Self {
+        let (text, emojis) = crate::emoji::parse::parse_out_emojis(
+            text,
+            font.emoji_options.parse_shortcodes,
+            font.emoji_options.parse_discord_emojis,
+        );
+
+        Self::parsed_flowed_with_emojis(
+            &text,
+            &emojis,
+            &mut 0,
+            shape,
+            font,
+            scale,
+            line_spacing,
+            align,
+            wrap_style,
+            options,
+        )
+    }
+
+    /// Flows `text` in `shape` and lays the lines out.
+    ///
+    /// assumes that emojis were parsed out
+    #[allow(clippy::too_many_arguments)]
+    pub fn parsed_flowed_with_emojis(
+        text: &str,
+        emojis: &[crate::emoji::source::EmojiType],
+        emoji_idx: &mut usize,
+        shape: &FlowShape,
+        font: &SuperFont<'font>,
+        scale: rusttype::Scale,
+        line_spacing: f32,
+        align: TextAlign,
+        wrap_style: WrapStyle,
+        options: &LayoutOptions,
+    ) -> Self {
+        let lines = text_flow(
+            text,
+            shape,
+            font,
+            scale,
+            line_spacing,
+            wrap_style,
+            options,
+            |scale, font, text| {
+                TextLayout::parsed_ink_width_with_emojis(text, font, scale, options)
+            },
+        );
+
+        let mut builder = LayoutBuilder::new(font, scale, options)
+            .with_directions(paragraph_directions(
+                text,
+                lines.iter().map(|l| &l.text),
+                options.white_space,
+            ))
+            .with_emojis(emojis, *emoji_idx);
+        let layout = builder.flow(&lines, line_spacing, align);
+
+        *emoji_idx = builder.emoji_idx;
+        layout
+    }
+}
+
+struct LayoutBuilder<'a, 'font> {
+    font: &'a SuperFont<'font>,
+    scale: rusttype::Scale,
+    options: &'a LayoutOptions,
+    // the base direction of every line, found from the line itself when missing
+    directions: Vec<bool>,
+
+    #[cfg(feature = "emoji")]
+    emojis: Option<&'a [crate::emoji::source::EmojiType]>,
+    #[cfg(feature = "emoji")]
+    emoji_idx: usize,
+}
+
+impl<'a, 'font> LayoutBuilder<'a, 'font> {
+    fn new(font: &'a SuperFont<'font>, scale: rusttype::Scale, options: &'a LayoutOptions) -> Self {
+        Self {
+            font,
+            scale,
+            options,
+            directions: Vec::new(),
+            #[cfg(feature = "emoji")]
+            emojis: None,
+            #[cfg(feature = "emoji")]
+            emoji_idx: 0,
+        }
+    }
+
+    fn with_directions(mut self, directions: Vec<bool>) -> Self {
+        self.directions = directions;
+        self
+    }
+
+    #[cfg(feature = "emoji")]
+    fn with_emojis(
+        mut self,
+        emojis: &'a [crate::emoji::source::EmojiType],
+        emoji_idx: usize,
+    ) -> Self {
+        self.emojis = Some(emojis);
+        self.emoji_idx = emoji_idx;
+        self
+    }
+
+    fn ink_width(&mut self, text: &str) -> i32 {
+        let rtl = self.direction(0, text, false);
+        self.line(text, 0.0, rtl).width as i32
+    }
+
+    fn single(&mut self, text: &str) -> TextLayout<'font> {
+        let line = self.line(text, 0.0, self.direction(0, text, false));
+
+        let (width, height) = match self.options.anchor_mode {
+            AnchorMode::Ink => {
+                let height = line
+                    .glyphs
+                    .iter()
+                    .filter_map(|g| g.glyph.pixel_bounding_box())
+                    .fold(0, |h, bb| h.max(bb.max.y));
+
+                (line.width, height as f32)
+            }
+            AnchorMode::Metrics => {
+                let v_metrics = self.font.main.v_metrics(self.scale);
+                (line.advance, v_metrics.ascent - v_metrics.descent)
+            }
+        };
+
+        TextLayout {
+            width,
+            height,
+            scale: self.scale,
+            line_height: self.scale.y,
+            lines: vec![line],
+            font: self.font.clone(),
+            options: self.options.clone(),
+        }
+    }
+
+    fn multiline(
+        &mut self,
+        lines: &[String],
+        line_spacing: f32,
+        align: TextAlign,
+        width: Option<f32>,
+    ) -> TextLayout<'font> {
+        let line_height = self.scale.y * line_spacing;
+
+        // lines without a strong character keep the direction of the line before them
+        let mut rtl = false;
+        let mut lines = lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| {
+                rtl = self.direction(i, line, rtl);
+                self.line(line, i as f32 * line_height, rtl)
+            })
+            .collect::<Vec<_>>();
+
+        let width = width.unwrap_or_else(|| lines.iter().fold(0.0, |w, l| self.measure(l).max(w)));
+
+        let count = lines.len();
+        for (i, line) in lines.iter_mut().enumerate() {
+            self.align(line, 0.0, width, align, align.justifies(i, count));
+        }
+
+        let height = self.height(&lines);
+
+        TextLayout {
+            width,
+            height,
+            scale: self.scale,
+            line_height,
+            lines,
+            font: self.font.clone(),
+            options: self.options.clone(),
+        }
+    }
+
+    fn flow(
+        &mut self,
+        lines: &[FlowLine],
+        line_spacing: f32,
+        align: TextAlign,
+    ) -> TextLayout<'font> {
+        let count = lines.len();
+        let mut rtl = false;
+
+        let layout_lines = lines
+            .iter()
+            .enumerate()
+            .map(|(i, l)| {
+                rtl = self.direction(i, &l.text, rtl);
+
+                let mut line = self.line(&l.text, l.top, rtl);
+                self.align(&mut line, l.x, l.width, align, align.justifies(i, count));
+                line
+            })
+            .collect::<Vec<_>>();
+
+        TextLayout {
+            width: lines.iter().fold(0.0, |w, l| (l.x + l.width).max(w)),
+            height: self.height(&layout_lines),
+            scale: self.scale,
+            line_height: self.scale.y * line_spacing,
+            lines: layout_lines,
+            font: self.font.clone(),
+            options: self.options.clone(),
+        }
+    }
+
+    // the base direction of the line `i`, lines without a strong character keep the
+    // direction `rtl` of the line before them
+    fn direction(&self, i: usize, text: &str, rtl: bool) -> bool {
+        match self.directions.get(i) {
+            Some(rtl) => *rtl,
+            None => base_direction_rtl(text).unwrap_or(rtl),
+        }
+    }
+
+    fn measure(&self, line: &LayoutLine) -> f32 {
+        match self.options.anchor_mode {
+            AnchorMode::Ink => line.width,
+            AnchorMode::Metrics => line.advance,
+        }
+    }
+
+    // the height of lines, from the top of the first one to the bottom of the last one
+    fn height(&self, lines: &[LayoutLine]) -> f32 {
+        let Some(last) = lines.last() else {
+            return 0.0;
+        };
+
+        match self.options.anchor_mode {
+            AnchorMode::Ink => last.top + self.scale.y,
+            AnchorMode::Metrics => {
+                let v_metrics = self.font.main.v_metrics(self.scale);
+                last.top + v_metrics.ascent - v_metrics.descent
+            }
         }
     }
+
+    // moves a line laid out at 0 to the span from `x` to `x + width`, aligned in it
+    fn align(
+        &self,
+        line: &mut LayoutLine<'font>,
+        x: f32,
+        width: f32,
+        align: TextAlign,
+        justify: bool,
+    ) {
+        if justify {
+            line.justify(width - self.measure(line), self.options.justify_cjk);
+        }
+
+        let ax = align.anchor(line.rtl);
+        line.x = x + width * ax - self.measure(line) * ax;
+
+        if line.x != 0.0 {
+            for g in line.glyphs.iter_mut() {
+                let p = g.glyph.position();
+                g.glyph.set_position(rusttype::point(p.x + line.x, p.y));
+            }
+        }
+    }
+
+    // lays a line out with its left edge at 0
+    fn line(&mut self, text: &str, top: f32, rtl: bool) -> LayoutLine<'font> {
+        let baseline = top + self.font.main.v_metrics(self.scale).ascent;
+
+        #[cfg(feature = "emoji")]
+        let emoji_scale = self.emojis.map(|_| rusttype::Scale {
+            x: self.font.emoji_options.scale * self.scale.x,
+            y: self.font.emoji_options.scale * self.scale.y,
+        });
+        #[cfg(not(feature = "emoji"))]
+        let emoji_scale = None;
+
+        #[cfg(feature = "emoji")]
+        let (placeholders, emoji_base) = match self.emojis {
+            Some(_) => (
+                text.match_indices(crate::emoji::parse::PLACEHOLDER_EMOJI)
+                    .map(|(i, _)| i)
+                    .collect::<Vec<_>>(),
+                self.emoji_idx,
+            ),
+            None => (Vec::new(), self.emoji_idx),
+        };
+
+        let level = match rtl {
+            true => Level::rtl(),
+            false => Level::ltr(),
+        };
+        let shaped = shape(
+            self.font,
+            text,
+            self.scale,
+            emoji_scale,
+            Some(level),
+            &self.options.tab_stops,
+        );
+
+        let mut caret = 0.0;
+        let mut width = 0;
+        let mut glyphs = Vec::with_capacity(shaped.len());
+        let mut runs: Vec<GlyphRun<'font>> = Vec::new();
+
+        for g in &shaped {
+            let glyph = position(g, &mut caret, rusttype::point(0.0, baseline));
+
+            if let Some(bb) = glyph.pixel_bounding_box() {
+                width = width.max(bb.max.x);
+            }
+
+            #[cfg(feature = "emoji")]
+            let emoji = match self.emojis {
+                Some(emojis) if g.font_index == crate::shaping::EMOJI_FONT_INDEX => {
+                    match placeholders.binary_search(&g.cluster) {
+                        Ok(ordinal) => {
+                            self.emoji_idx += 1;
+                            emojis.get(emoji_base + ordinal).copied()
+                        }
+                        Err(_) => None,
+                    }
+                }
+                _ => None,
+            };
+
+            match runs.last_mut() {
+                Some(run)
+                    if run.rtl == g.rtl && shaped[run.glyphs.start].font_index == g.font_index =>
+                {
+                    run.glyphs.end += 1;
+                }
+                _ => runs.push(GlyphRun {
+                    font: g.font.clone(),
+                    glyphs: glyphs.len()..glyphs.len() + 1,
+                    rtl: g.rtl,
+                }),
+            }
+
+            glyphs.push(LayoutGlyph {
+                glyph,
+                cluster: g.cluster,
+                advance: g.x_advance,
+                #[cfg(feature = "emoji")]
+                emoji,
+            });
+        }
+
+        LayoutLine {
+            text: text.to_string(),
+            glyphs,
+            runs,
+            x: 0.0,
+            top,
+            baseline,
+            width: width as f32,
+            advance: caret,
+            rtl,
+        }
+    }
+}
+
+#[cfg(feature = "emoji")]
+#[test]
+fn text_layout_test() {
+    use crate::emoji::source::EmojiType;
+
+    let font = SuperFont::new(crate::emoji::EMOJI_FONT.clone(), vec![]);
+    let scale = rusttype::Scale::uniform(20.0);
+    let options = LayoutOptions::default();
+
+    let lines = vec!["1".to_string(), "1 11".to_string()];
+    let layout = TextLayout::multiline(
+        &lines,
+        &font,
+        scale,
+        1.5,
+        TextAlign::Right,
+        Some(100.0),
+        &options,
+    );
+
+    assert_eq!(layout.lines.len(), 2);
+    assert_eq!(layout.lines[1].top, 30.0);
+    for line in &layout.lines {
+        assert_eq!(line.x + line.width, 100.0);
+        assert_eq!(line.runs.len(), 1);
+    }
+    assert_eq!(layout.height, 50.0);
+
+    let lines = vec!["1 1 1".to_string(), "11".to_string(), "1 1".to_string()];
+    let layout = TextLayout::multiline(
+        &lines,
+        &font,
+        scale,
+        1.0,
+        TextAlign::Justify,
+        Some(100.0),
+        &options,
+    );
+    assert_eq!((layout.lines[0].x, layout.lines[0].width), (0.0, 100.0));
+    assert_eq!((layout.lines[1].x, layout.lines[1].width), (0.0, 35.0));
+    assert!(layout.lines[2].width < 100.0);
+
+    let text = format!("1{0}1{0}", crate::emoji::parse::PLACEHOLDER_EMOJI);
+    let emojis = [
+        EmojiType::Discord(1),
+        EmojiType::Discord(2),
+        EmojiType::Discord(3),
+    ];
+    let mut emoji_idx = 1;
+    let layout =
+        TextLayout::parsed_with_emojis(&text, &emojis, &mut emoji_idx, &font, scale, &options);
+
+    assert_eq!(emoji_idx, 3);
+    assert_eq!(
+        layout.glyphs().filter_map(|g| g.emoji).collect::<Vec<_>>(),
+        vec![EmojiType::Discord(2), EmojiType::Discord(3)]
+    );
+}
+
+#[cfg(feature = "emoji")]
+#[test]
+fn justify_test() {
+    use crate::outliner::AnchorMode;
+
+    let font = SuperFont::new(crate::emoji::EMOJI_FONT.clone(), vec![]);
+    let scale = rusttype::Scale::uniform(20.0);
+    let options = LayoutOptions::default();
+    let xs = |line: &LayoutLine| {
+        line.glyphs
+            .iter()
+            .map(|g| g.glyph.position().x)
+            .collect::<Vec<_>>()
+    };
+
+    // the extra space is shared by the gaps between words, the last line isn't stretched
+    let lines = vec!["1 1 1".to_string(), "1 1".to_string()];
+    let multiline = |align, options: &LayoutOptions| {
+        TextLayout::multiline(&lines, &font, scale, 1.0, align, Some(100.0), options)
+    };
+    let left = multiline(TextAlign::Left, &options);
+    let justified = multiline(TextAlign::Justify, &options);
+
+    let line = &justified.lines[0];
+    let gap = (100.0 - left.lines[0].width) / 2.0;
+    assert_eq!((line.x, line.width), (0.0, 100.0));
+    let shifts = xs(line)
+        .iter()
+        .zip(xs(&left.lines[0]))
+        .map(|(x, left)| x - left)
+        .collect::<Vec<_>>();
+    assert_eq!(shifts, vec![0.0, 0.0, gap, gap, gap * 2.0]);
+    assert_eq!(xs(&justified.lines[1]), xs(&left.lines[1]));
+
+    let all = multiline(TextAlign::JustifyAll, &options);
+    assert_eq!(all.lines[1].width, 100.0);
+
+    // the last line starts on the right when it's right to left
+    let lines = vec!["1 1".to_string(), "א 1".to_string()];
+    let justified = TextLayout::multiline(
+        &lines,
+        &font,
+        scale,
+        1.0,
+        TextAlign::Justify,
+        Some(100.0),
+        &options,
+    );
+    let last = &justified.lines[1];
+    assert!(last.rtl && last.width < 100.0);
+    assert_eq!(last.x + last.width, 100.0);
+
+    // CJK characters are only spread apart when asked to
+    let lines = vec!["一二三".to_string(), String::new()];
+    let options = options.with_anchor_mode(AnchorMode::Metrics);
+    let advance = |options: &LayoutOptions| {
+        TextLayout::multiline(
+            &lines,
+            &font,
+            scale,
+            1.0,
+            TextAlign::Justify,
+            Some(100.0),
+            options,
+        )
+        .lines[0]
+            .advance
+    };
+    assert!(advance(&options) < 100.0);
+    assert_eq!(advance(&options.clone().with_justify_cjk(true)), 100.0);
+}
+
+#[cfg(feature = "emoji")]
+#[test]
+fn text_decoration_test() {
+    use crate::outliner::TextDecoration;
+
+    let font = SuperFont::new(crate::emoji::EMOJI_FONT.clone(), vec![]);
+    let scale = rusttype::Scale::uniform(20.0);
+    let options = LayoutOptions::default();
+
+    let lines = vec!["11 22".to_string(), String::new(), "1".to_string()];
+    let layout =
+        TextLayout::multiline(&lines, &font, scale, 1.0, TextAlign::Center, None, &options);
+    assert!(layout.decorations().is_empty());
+
+    let options = options.with_decoration(TextDecoration {
+        underline: true,
+        strikethrough: true,
+        ..Default::default()
+    });
+    let layout =
+        TextLayout::multiline(&lines, &font, scale, 1.0, TextAlign::Center, None, &options);
+    let rects = layout.decorations();
+
+    // the empty line isn't decorated
+    assert_eq!(rects.len(), 4);
+    let line = &layout.lines[2];
+    let (underline, strikethrough) = (rects[2], rects[3]);
+    assert!(underline.min.y > line.baseline);
+    assert!(strikethrough.max.y < line.baseline);
+    assert!(strikethrough.max.y > line.baseline - scale.y / 2.0);
+    assert_eq!(
+        (underline.min.x, underline.max.x),
+        (line.x, line.x + line.advance)
+    );
+    assert!(underline.height() >= 1.0);
+
+    // skipping ink through the middle of the digits leaves the gaps between them
+    let line = &layout.lines[0];
+    let spans = skip_ink(
+        line,
+        line.x - 5.0,
+        line.x + line.advance + 5.0,
+        8.0,
+        9.0,
+        1.0,
+    );
+    let boxes = line
+        .glyphs
+        .iter()
+        .filter_map(|g| g.glyph.pixel_bounding_box())
+        .collect::<Vec<_>>();
+    assert_eq!(spans.len(), boxes.len() + 1);
+    for bb in boxes {
+        let center = (bb.min.x + bb.max.x) as f32 / 2.0;
+        assert!(spans.iter().all(|(x0, x1)| *x1 < center || *x0 > center));
+    }
+}
+
+#[cfg(feature = "emoji")]
+#[test]
+fn bidi_layout_test() {
+    let font = SuperFont::new(crate::emoji::EMOJI_FONT.clone(), vec![]);
+    let scale = rusttype::Scale::uniform(20.0);
+    let options = LayoutOptions::default();
+    let clusters = |line: &LayoutLine| line.glyphs.iter().map(|g| g.cluster).collect::<Vec<_>>();
+
+    // the hebrew run and the digits after it are reversed, the digits keep their order
+    let text = "a אב 12";
+    let line = &TextLayout::new(text, &font, scale, &options).lines[0];
+    assert!(!line.rtl);
+    assert_eq!(clusters(line), vec![0, 1, 7, 8, 6, 4, 2]);
+
+    // text that starts right to left is laid out from the right
+    let text = "אב 12";
+    let line = &TextLayout::new(text, &font, scale, &options).lines[0];
+    assert!(line.rtl);
+    assert_eq!(clusters(line), vec![5, 6, 4, 2, 0]);
+
+    // start and end follow the direction of every line
+    let lines = vec!["11".to_string(), "א 11".to_string()];
+    let start = TextLayout::multiline(
+        &lines,
+        &font,
+        scale,
+        1.0,
+        TextAlign::Start,
+        Some(100.0),
+        &options,
+    );
+    assert_eq!(start.lines[0].x, 0.0);
+    assert_eq!(start.lines[1].x + start.lines[1].width, 100.0);
+
+    let end = TextLayout::multiline(
+        &lines,
+        &font,
+        scale,
+        1.0,
+        TextAlign::End,
+        Some(100.0),
+        &options,
+    );
+    assert_eq!(end.lines[0].x + end.lines[0].width, 100.0);
+    assert_eq!(end.lines[1].x, 0.0);
+
+    // a wrapped line keeps the direction of its paragraph when it starts with a word of
+    // the other direction
+    let options = options.with_white_space(WhiteSpace::PreLine);
+    let text = "א 1111 ab, אב\nb";
+    let width = crate::measure::text_width(scale, &font, "א 1111") as f32;
+    let layout = TextLayout::wrapped(
+        text,
+        width,
+        &font,
+        scale,
+        1.0,
+        TextAlign::Start,
+        WrapStyle::Word,
+        &options,
+    );
+    let texts = layout
+        .lines
+        .iter()
+        .map(|l| l.text.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(texts, vec!["א 1111", "ab, אב", "b"]);
+
+    let line = &layout.lines[1];
+    assert!(line.rtl);
+    assert_eq!(clusters(line), vec![6, 4, 3, 2, 0, 1]);
+    assert_eq!(line.x + line.width, width);
+    assert!(!layout.lines[2].rtl);
+}
+
+#[cfg(feature = "emoji")]
+#[test]
+fn vertical_layout_test() {
+    use crate::drawing::{outline::Outline, text::draw_text_vertical_multiline};
+    use crate::measure::{text_height_vertical, text_size_vertical_multiline, text_width};
+    use crate::wrap::text_wrap;
+
+    let font = SuperFont::new(crate::emoji::EMOJI_FONT.clone(), vec![]);
+    let scale = rusttype::Scale::uniform(20.0);
+
+    // digits are sideways, they go down the column and are rotated a quarter turn
+    let glyphs = font
+        .layout_vertical("111", scale, rusttype::point(10.0, 0.0))
+        .collect::<Vec<_>>();
+    assert!(glyphs.iter().all(|g| g.sideways));
+    assert!(glyphs
+        .windows(2)
+        .all(|w| w[1].glyph.position().y > w[0].glyph.position().y));
+
+    let upright = font
+        .layout("1", scale, rusttype::point(0.0, 0.0))
+        .find_map(|g| g.pixel_bounding_box())
+        .unwrap();
+    let sideways = glyphs[0].pixel_bounding_box().unwrap();
+    assert!(sideways.width().abs_diff(upright.height()) <= 1);
+    assert!(sideways.height().abs_diff(upright.width()) <= 1);
+
+    // a column height limit wraps like a width limit
+    let text = "11 1 111 11 1 11 1 111 1";
+    let columns = text_wrap(
+        text,
+        80,
+        &font,
+        scale,
+        WrapStyle::Word,
+        text_height_vertical,
+    );
+    assert!(columns.len() > 2);
+    assert_eq!(
+        columns,
+        text_wrap(text, 80, &font, scale, WrapStyle::Word, text_width)
+    );
+    for column in &columns {
+        assert!(text_height_vertical(scale, &font, column) <= 80);
+    }
+
+    // the first column is the rightmost one
+    let lines = vec!["1".to_string(), "1111".to_string()];
+    let (w, h) = text_size_vertical_multiline(&lines, &font, scale, 1.0);
+    assert_eq!(w, 40);
+
+    let mut image = image::RgbaImage::new(w as u32, 100);
+    draw_text_vertical_multiline(
+        &mut image,
+        &tiny_skia::Paint::default(),
+        Outline::None,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        100.0,
+        scale,
+        &font,
+        &lines,
+        1.0,
+        TextAlign::Start,
+    )
+    .unwrap();
+
+    let bottom = |x0: u32, x1: u32| {
+        image
+            .enumerate_pixels()
+            .filter(|(x, _, p)| (x0..x1).contains(x) && p[3] > 0)
+            .map(|(_, y, _)| y)
+            .max()
+    };
+    let (left, right) = (bottom(0, 20).unwrap(), bottom(20, 40).unwrap());
+    assert!(right < 25);
+    assert!(left > 60 && left as i32 <= h);
 }
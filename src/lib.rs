@@ -1,9 +1,21 @@
 pub mod drawing;
+pub mod features;
+pub mod fit;
+pub mod flow;
+pub mod frames;
+pub mod hit_test;
+pub mod hyphenation;
 pub mod layout;
+pub mod markup;
 pub mod measure;
 pub mod outliner;
 mod render;
+pub mod rich;
+mod shaping;
 pub mod superfont;
+pub mod tabs;
+pub mod truncate;
+pub mod variations;
 pub mod wrap;
 
 pub mod prelude {
@@ -11,10 +23,22 @@ pub mod prelude {
     pub use crate::drawing::paint::*;
     pub use crate::drawing::text::*;
     pub use crate::drawing::utils::*;
+    pub use crate::features::FontFeatures;
+    pub use crate::fit::*;
+    pub use crate::flow::*;
+    pub use crate::frames::*;
+    pub use crate::hit_test::*;
+    pub use crate::hyphenation::Language;
+    pub use crate::layout::{LayoutOptions, TextLayout};
+    pub use crate::markup::*;
     pub use crate::measure::*;
+    pub use crate::rich::*;
+    pub use crate::tabs::*;
+    pub use crate::truncate::*;
+    pub use crate::variations::FontVariations;
     pub use crate::wrap::*;
 
-    pub use crate::outliner::TextAlign;
+    pub use crate::outliner::{AnchorMode, TextAlign, TextDecoration};
     pub use crate::superfont::*;
 
     pub use rusttype::{Font, Scale};
@@ -0,0 +1,504 @@
+use std::{cell::OnceCell, rc::Rc};
+
+use tiny_skia::Paint;
+
+use crate::{
+    drawing::outline::Outline,
+    rich::TextSpan,
+    superfont::{SuperFont, SyntheticStyle},
+    variations::{has_axis, FontVariations},
+};
+
+/// The fonts markup is drawn with. `<b>` and `<i>` pick the bold and italic fonts. When
+/// they aren't set, the regular font is set to its `wght` (700) and `slnt` (-12) axes, and
+/// fonts without those axes get a [`SyntheticStyle`] instead.
+#[derive(Clone)]
+pub struct MarkupFonts<'f> {
+    pub regular: SuperFont<'f>,
+    pub bold: Option<SuperFont<'f>>,
+    pub italic: Option<SuperFont<'f>>,
+    pub bold_italic: Option<SuperFont<'f>>,
+}
+
+impl<'f> MarkupFonts<'f> {
+    pub fn new(regular: SuperFont<'f>) -> Self {
+        Self {
+            regular,
+            bold: None,
+            italic: None,
+            bold_italic: None,
+        }
+    }
+
+    pub fn with_bold(mut self, bold: SuperFont<'f>) -> Self {
+        self.bold = Some(bold);
+        self
+    }
+
+    pub fn with_italic(mut self, italic: SuperFont<'f>) -> Self {
+        self.italic = Some(italic);
+        self
+    }
+
+    pub fn with_bold_italic(mut self, bold_italic: SuperFont<'f>) -> Self {
+        self.bold_italic = Some(bold_italic);
+        self
+    }
+
+    fn font(&self, bold: bool, italic: bool) -> SuperFont<'f> {
+        let synthetic = |font: &SuperFont<'f>, bold: bool, italic: bool| {
+            let mut variations = FontVariations::new();
+            if bold {
+                variations = variations.weight(700.0);
+            }
+            if italic {
+                variations = variations.slant(-12.0);
+            }
+
+            let style = SyntheticStyle {
+                bold: font.synthetic.bold || bold && !has_axis(&font.main, "wght"),
+                italic: font.synthetic.italic || italic && !has_axis(&font.main, "slnt"),
+            };
+            font.clone()
+                .with_variations(&variations)
+                .with_synthetic(style)
+        };
+
+        match (bold, italic) {
+            (false, false) => self.regular.clone(),
+            (true, false) => self
+                .bold
+                .clone()
+                .unwrap_or_else(|| synthetic(&self.regular, true, false)),
+            (false, true) => self
+                .italic
+                .clone()
+                .unwrap_or_else(|| synthetic(&self.regular, false, true)),
+            (true, true) => self
+                .bold_italic
+                .clone()
+                .unwrap_or_else(|| match &self.bold {
+                    Some(bold) => synthetic(bold, false, true),
+                    None => synthetic(&self.regular, true, true),
+                }),
+        }
+    }
+
+    #[cfg(feature = "fontdb")]
+    // the fonts named `query` in the `FontDB`, their bold and italic fonts are the ones named
+    // with a `-Bold`, `-Italic` (or `-Oblique`) and `-BoldItalic` suffix. They keep the
+    // settings of `base`.
+    fn from_db(query: &str, base: &SuperFont<'f>) -> Option<Self> {
+        let names = query.split_whitespace().collect::<Vec<_>>();
+        let styled = |suffixes: &[&str]| {
+            suffixes.iter().find_map(|suffix| {
+                let names = names
+                    .iter()
+                    .map(|name| format!("{name}{suffix}"))
+                    .collect::<Vec<_>>();
+                let names = names.iter().map(String::as_str).collect::<Vec<_>>();
+
+                crate::fontdb::FontDB::superfont(&names).map(|font| SuperFont {
+                    main: font.main,
+                    fallbacks: font.fallbacks,
+                    faces: font.faces,
+                    ..base.clone()
+                })
+            })
+        };
+
+        Some(Self {
+            regular: styled(&[""])?,
+            bold: styled(&["-Bold"]),
+            italic: styled(&["-Italic", "-Oblique"]),
+            bold_italic: styled(&["-BoldItalic", "-BoldOblique"]),
+        })
+    }
+}
+
+// the fonts of a markup, and its styled fonts made once and shared by all their runs
+struct StyledFonts<'f> {
+    fonts: MarkupFonts<'f>,
+    styles: [OnceCell<SuperFont<'f>>; 4],
+}
+
+impl<'f> StyledFonts<'f> {
+    fn new(fonts: MarkupFonts<'f>) -> Rc<Self> {
+        Rc::new(Self {
+            fonts,
+            styles: Default::default(),
+        })
+    }
+
+    fn font(&self, bold: bool, italic: bool) -> SuperFont<'f> {
+        self.styles[bold as usize * 2 + italic as usize]
+            .get_or_init(|| self.fonts.font(bold, italic))
+            .clone()
+    }
+}
+
+/// A run of markup text, with the style of the tags it's in.
+#[derive(Clone)]
+pub struct MarkupRun<'a, 'f> {
+    pub text: String,
+    pub font: SuperFont<'f>,
+    pub scale: rusttype::Scale,
+    pub fill: Paint<'a>,
+}
+
+// the style at a point of the markup, and the tag that set it
+#[derive(Clone)]
+struct MarkupState<'a, 'f> {
+    tag: &'static str,
+    fonts: Rc<StyledFonts<'f>>,
+    bold: bool,
+    italic: bool,
+    scale: rusttype::Scale,
+    fill: Paint<'a>,
+}
+
+/// Parses markup into runs of text styled by its tags, starting from the regular font of
+/// `fonts`, `scale` and `fill`:
+/// - `<b>...</b>` and `<i>...</i>`, see [`MarkupFonts`]
+/// - `<color=#f80>...</color>`, as `#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa`
+/// - `<size=1.5>...</size>` relative to the enclosing size, or `<size=24px>...</size>`
+/// - `<font=name>...</font>`, fonts (and fallbacks separated by spaces) from the `FontDB`
+///
+/// Tags must be closed in order, tags left open at the end are closed. `\<` is a literal
+/// `<`, and a `<` that doesn't start one of these tags is kept as text. Emojis are left in
+/// the text, to be parsed when drawing with emojis.
+pub fn parse_markup<'a, 'f>(
+    markup: &str,
+    fonts: &MarkupFonts<'f>,
+    scale: rusttype::Scale,
+    fill: &Paint<'a>,
+) -> Result<Vec<MarkupRun<'a, 'f>>, &'static str> {
+    let mut stack = vec![MarkupState {
+        tag: "",
+        fonts: StyledFonts::new(fonts.clone()),
+        bold: false,
+        italic: false,
+        scale,
+        fill: fill.clone(),
+    }];
+    let mut runs = Vec::new();
+    let mut text = String::new();
+    let mut rest = markup;
+
+    while let Some(i) = rest.find(['<', '\\']) {
+        text.push_str(&rest[..i]);
+        rest = &rest[i..];
+
+        if let Some(after) = rest.strip_prefix("\\<") {
+            text.push('<');
+            rest = after;
+            continue;
+        }
+
+        let Some((tag, after)) = rest.strip_prefix('<').and_then(parse_tag) else {
+            // a lone backslash or `<`
+            text.push_str(&rest[..1]);
+            rest = &rest[1..];
+            continue;
+        };
+        rest = after;
+
+        let state = stack.last().expect("the base state is never popped");
+        if !text.is_empty() {
+            runs.push(MarkupRun {
+                text: std::mem::take(&mut text),
+                font: state.fonts.font(state.bold, state.italic),
+                scale: state.scale,
+                fill: state.fill.clone(),
+            });
+        }
+
+        let mut state = state.clone();
+        match tag {
+            Tag::Close(name) => {
+                if state.tag != name {
+                    return Err(if stack.iter().any(|s| s.tag == name) {
+                        "Mismatched closing tag"
+                    } else {
+                        "Unmatched closing tag"
+                    });
+                }
+                stack.pop();
+                continue;
+            }
+            Tag::Bold => state.bold = true,
+            Tag::Italic => state.italic = true,
+            Tag::Color(value) => state.fill = parse_color(value)?,
+            Tag::Size(value) => state.scale = parse_size(value, state.scale)?,
+            Tag::Font(query) => {
+                state.fonts = StyledFonts::new(markup_fonts(query, &state.fonts.fonts.regular)?)
+            }
+        }
+
+        state.tag = tag.name();
+        stack.push(state);
+    }
+    text.push_str(rest);
+
+    if !text.is_empty() {
+        let state = stack.last().expect("the base state is never popped");
+        runs.push(MarkupRun {
+            text,
+            font: state.fonts.font(state.bold, state.italic),
+            scale: state.scale,
+            fill: state.fill.clone(),
+        });
+    }
+
+    Ok(runs)
+}
+
+/// The spans of parsed markup, for [`RichLayout`](crate::rich::RichLayout) and
+/// [`rich_text_wrap`](crate::rich::rich_text_wrap), all drawn with `outline`.
+pub fn markup_spans<'r, 'f>(
+    runs: &'r [MarkupRun<'_, 'f>],
+    outline: Outline<'r>,
+) -> Vec<TextSpan<'r, 'f>> {
+    runs.iter()
+        .map(|run| {
+            TextSpan::new(&run.text, &run.font, run.scale, &run.fill).with_outline(outline.clone())
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Tag<'m> {
+    Bold,
+    Italic,
+    Color(&'m str),
+    Size(&'m str),
+    Font(&'m str),
+    Close(&'static str),
+}
+
+impl Tag<'_> {
+    fn name(&self) -> &'static str {
+        match self {
+            Tag::Bold => "b",
+            Tag::Italic => "i",
+            Tag::Color(_) => "color",
+            Tag::Size(_) => "size",
+            Tag::Font(_) => "font",
+            Tag::Close(name) => name,
+        }
+    }
+}
+
+// the tag at the start of `s` (after its `<`), and the text after it
+fn parse_tag(s: &str) -> Option<(Tag<'_>, &str)> {
+    let (tag, rest) = s.split_once('>')?;
+
+    let tag = match tag.split_once('=') {
+        Some(("color", value)) => Tag::Color(value),
+        Some(("size", value)) => Tag::Size(value),
+        Some(("font", value)) => Tag::Font(value),
+        Some(_) => return None,
+        None => match tag {
+            "b" => Tag::Bold,
+            "i" => Tag::Italic,
+            "/b" => Tag::Close("b"),
+            "/i" => Tag::Close("i"),
+            "/color" => Tag::Close("color"),
+            "/size" => Tag::Close("size"),
+            "/font" => Tag::Close("font"),
+            _ => return None,
+        },
+    };
+
+    Some((tag, rest))
+}
+
+fn parse_color(value: &str) -> Result<Paint<'static>, &'static str> {
+    let hex = value.strip_prefix('#').ok_or("Invalid color")?;
+    let digit = |i: usize| {
+        hex.get(i..i + 1)
+            .and_then(|d| u8::from_str_radix(d, 16).ok())
+            .ok_or("Invalid color")
+    };
+
+    let rgba = match hex.len() {
+        3 | 4 => {
+            let channel = |i: usize| digit(i).map(|d| d * 17);
+            let a = if hex.len() == 4 { channel(3)? } else { 255 };
+            [channel(0)?, channel(1)?, channel(2)?, a]
+        }
+        6 | 8 => {
+            let channel = |i: usize| Ok::<u8, &'static str>(digit(i)? * 16 + digit(i + 1)?);
+            let a = if hex.len() == 8 { channel(6)? } else { 255 };
+            [channel(0)?, channel(2)?, channel(4)?, a]
+        }
+        _ => return Err("Invalid color"),
+    };
+
+    Ok(crate::drawing::paint::paint_from_rgba_slice(&rgba))
+}
+
+fn parse_size(value: &str, scale: rusttype::Scale) -> Result<rusttype::Scale, &'static str> {
+    let (size, px) = match value.strip_suffix("px") {
+        Some(px) => (px, true),
+        None => (value, false),
+    };
+
+    match size.trim().parse::<f32>() {
+        Ok(size) if size > 0.0 && size.is_finite() => Ok(if px {
+            rusttype::Scale::uniform(size)
+        } else {
+            rusttype::Scale {
+                x: scale.x * size,
+                y: scale.y * size,
+            }
+        }),
+        _ => Err("Invalid size"),
+    }
+}
+
+#[cfg(feature = "fontdb")]
+fn markup_fonts<'f>(query: &str, base: &SuperFont<'f>) -> Result<MarkupFonts<'f>, &'static str> {
+    MarkupFonts::from_db(query, base).ok_or("Unknown font")
+}
+
+#[cfg(not(feature = "fontdb"))]
+fn markup_fonts<'f>(_query: &str, _base: &SuperFont<'f>) -> Result<MarkupFonts<'f>, &'static str> {
+    Err("Unknown font")
+}
+
+#[cfg(feature = "emoji")]
+#[test]
+fn parse_markup_test() {
+    let fonts = MarkupFonts::new(SuperFont::new(crate::emoji::EMOJI_FONT.clone(), vec![]));
+    let scale = rusttype::Scale::uniform(20.0);
+    let fill = crate::drawing::paint::BLACK;
+
+    let runs = parse_markup(
+        "1<b>2</b><color=#f00>3<size=2>4</size><size=30px>5</size></color> \\<6 <3",
+        &fonts,
+        scale,
+        &fill,
+    )
+    .unwrap();
+
+    let texts = runs.iter().map(|run| run.text.as_str()).collect::<Vec<_>>();
+    assert_eq!(texts, vec!["1", "2", "3", "4", "5", " <6 <3"]);
+
+    let sizes = runs.iter().map(|run| run.scale.y).collect::<Vec<_>>();
+    assert_eq!(sizes, vec![20.0, 20.0, 20.0, 40.0, 30.0, 20.0]);
+
+    let nested = parse_markup(
+        "<size=2>1<size=2>2</size><size=10px>3<size=0.5>4",
+        &fonts,
+        scale,
+        &fill,
+    )
+    .unwrap();
+    let sizes = nested.iter().map(|run| run.scale.y).collect::<Vec<_>>();
+    assert_eq!(sizes, vec![40.0, 80.0, 10.0, 5.0]);
+
+    let red = parse_color("#ff0000").unwrap();
+    assert_eq!(runs[2].fill.shader, red.shader);
+    assert_eq!(runs[4].fill.shader, red.shader);
+    assert_eq!(runs[5].fill.shader, fill.shader);
+    assert_eq!(parse_color("#f00c").unwrap().shader, {
+        parse_color("#ff0000cc").unwrap().shader
+    });
+
+    let parse = |markup| parse_markup(markup, &fonts, scale, &fill).map(|runs| runs.len());
+    assert_eq!(parse("<b>1"), Ok(1));
+    assert_eq!(parse("1</b>"), Err("Unmatched closing tag"));
+    assert_eq!(parse("<b><i>1</b></i>"), Err("Mismatched closing tag"));
+    assert_eq!(parse("<color=red>1"), Err("Invalid color"));
+    assert_eq!(parse("<size=-1>1"), Err("Invalid size"));
+    assert_eq!(parse("<font=not a font>1"), Err("Unknown font"));
+
+    #[cfg(feature = "fontdb")]
+    {
+        crate::fontdb::FontDB::insert("markup-test", crate::emoji::EMOJI_FONT.clone()).unwrap();
+        assert_eq!(parse("<font=markup-test>1</font>"), Ok(1));
+    }
+}
+
+#[test]
+fn synthetic_style_test() {
+    use crate::drawing::text::draw_text_layout;
+    use crate::layout::{LayoutOptions, TextLayout};
+    use crate::superfont::{static_test_font, test_font};
+
+    let scale = rusttype::Scale::uniform(40.0);
+    let fill = crate::drawing::paint::BLACK;
+    let synthetic = |bold, italic| SyntheticStyle { bold, italic };
+
+    // a static font has no axes, so bold and italic are faked
+    let fonts = MarkupFonts::new(SuperFont::new(static_test_font(), vec![]));
+    let runs = parse_markup(
+        "H<b>H</b><i>H</i><b><i>H</i></b><b>H</b>",
+        &fonts,
+        scale,
+        &fill,
+    )
+    .unwrap();
+    let styles = runs
+        .iter()
+        .map(|run| run.font.synthetic)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        styles,
+        vec![
+            synthetic(false, false),
+            synthetic(true, false),
+            synthetic(false, true),
+            synthetic(true, true),
+            synthetic(true, false),
+        ]
+    );
+
+    // Cantarell has a `wght` axis but no `slnt` one
+    let fonts = MarkupFonts::new(SuperFont::new(test_font(), vec![]));
+    let runs = parse_markup("<b>H</b><i>H</i><b>H</b>", &fonts, scale, &fill).unwrap();
+    assert_eq!(runs[0].font.synthetic, synthetic(false, false));
+    assert_eq!(runs[1].font.synthetic, synthetic(false, true));
+
+    // styled fonts are varied once per markup
+    assert!(std::ptr::eq(
+        crate::shaping::face(&runs[0].font.main),
+        crate::shaping::face(&runs[2].font.main),
+    ));
+
+    // the ink of each column of a drawn "H"
+    let draw = |style| {
+        let font = SuperFont::new(static_test_font(), vec![]).with_synthetic(style);
+        let layout = TextLayout::new("H", &font, scale, &LayoutOptions::default());
+        let mut image = image::RgbaImage::new(60, 60);
+        draw_text_layout(
+            &mut image,
+            &fill,
+            Outline::None,
+            10.0,
+            10.0,
+            0.0,
+            0.0,
+            &layout,
+        )
+        .unwrap();
+
+        (0..60)
+            .map(|x| {
+                (0..60)
+                    .map(|y| image.get_pixel(x, y)[3] as u32)
+                    .sum::<u32>()
+            })
+            .collect::<Vec<_>>()
+    };
+    let ink = |columns: &Vec<u32>| columns.iter().sum::<u32>();
+    let right = |columns: &Vec<u32>| columns.iter().rposition(|&a| a > 0).unwrap();
+
+    let regular = draw(synthetic(false, false));
+    let bold = draw(synthetic(true, false));
+    let italic = draw(synthetic(false, true));
+
+    assert!(ink(&bold) > ink(&regular) * 11 / 10);
+    assert!(right(&italic) > right(&regular) + 3);
+}
@@ -1,10 +1,91 @@
+use crate::layout::{LayoutOptions, TextLayout};
+use crate::outliner::TextAlign;
 use crate::superfont::SuperFont;
 
-pub fn text_size(scale: rusttype::Scale, font: &SuperFont, text: &str) -> (i32, i32) {
-    let v_metrics = font.main.v_metrics(scale);
+/// Font metric based measurements of a text, in pixels. Bounds are relative to the pen
+/// origin on the baseline (of the first line) with y pointing down.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextMetrics {
+    /// sum of the glyph advances, including spaces and letter spacing
+    pub advance: f32,
+    /// distance from the baseline to the top of the line (of the main font)
+    pub ascent: f32,
+    /// distance from the baseline to the bottom of the line, negative below the baseline
+    pub descent: f32,
+    pub line_gap: f32,
+
+    /// the bounds of the glyph outlines, `None` if nothing is inked
+    pub ink_bounds: Option<rusttype::Rect<f32>>,
+    /// the advance box of the text, from the ascent to the descent
+    pub logical_bounds: rusttype::Rect<f32>,
+}
+
+pub fn text_metrics(
+    scale: rusttype::Scale,
+    font: &SuperFont,
+    text: &str,
+    options: &LayoutOptions,
+) -> TextMetrics {
+    TextLayout::new(text, font, scale, options).metrics()
+}
+
+/// Returns the size used to anchor the text, see [`AnchorMode`](crate::outliner::AnchorMode).
+pub fn text_size(
+    scale: rusttype::Scale,
+    font: &SuperFont,
+    text: &str,
+    options: &LayoutOptions,
+) -> (i32, i32) {
+    TextLayout::new(text, font, scale, options).size()
+}
+
+/// Returns the width of the ink of the text, measured without [`LayoutOptions`] (tabs
+/// move to the default tab stops). It can be passed as the `width_fn` of the wrapping
+/// functions, see [`text_width_with_options`] to measure with options.
+pub fn text_width(scale: rusttype::Scale, font: &SuperFont, text: &str) -> i32 {
+    let mut w = 0;
+    for g in font.layout(text, scale, rusttype::point(0.0, 0.0)) {
+        if let Some(bb) = g.pixel_bounding_box() {
+            w = std::cmp::max(w, bb.max.x);
+        }
+    }
+    w
+}
+
+/// Same as [`text_width`], measuring the text like it's laid out with `options`.
+pub fn text_width_with_options(
+    scale: rusttype::Scale,
+    font: &SuperFont,
+    text: &str,
+    options: &LayoutOptions,
+) -> i32 {
+    TextLayout::ink_width(text, font, scale, options)
+}
+
+pub fn text_size_multiline(
+    lines: &[String],
+    font: &SuperFont,
+    scale: rusttype::Scale,
+    line_spacing: f32,
+    options: &LayoutOptions,
+) -> (i32, i32) {
+    TextLayout::multiline(
+        lines,
+        font,
+        scale,
+        line_spacing,
+        TextAlign::Start,
+        None,
+        options,
+    )
+    .size()
+}
+
+/// Returns the size of the text laid out as a vertical column.
+pub fn text_size_vertical(scale: rusttype::Scale, font: &SuperFont, text: &str) -> (i32, i32) {
     let (mut w, mut h) = (0, 0);
 
-    for g in font.layout(text, scale, rusttype::point(0.0, v_metrics.ascent)) {
+    for g in font.layout_vertical(text, scale, rusttype::point(scale.x / 2.0, 0.0)) {
         if let Some(bb) = g.pixel_bounding_box() {
             w = std::cmp::max(w, bb.max.x);
             h = std::cmp::max(h, bb.max.y);
@@ -13,32 +94,36 @@ pub fn text_size(scale: rusttype::Scale, font: &SuperFont, text: &str) -> (i32,
     (w, h)
 }
 
-pub fn text_width(scale: rusttype::Scale, font: &SuperFont, text: &str) -> i32 {
-    let mut w = 0;
-    for g in font.layout(text, scale, rusttype::point(0.0, 0.0)) {
+/// Returns the height of the text laid out as a vertical column, can be passed to
+/// `text_wrap` to break columns by height.
+pub fn text_height_vertical(scale: rusttype::Scale, font: &SuperFont, text: &str) -> i32 {
+    let mut h = 0;
+    for g in font.layout_vertical(text, scale, rusttype::point(0.0, 0.0)) {
         if let Some(bb) = g.pixel_bounding_box() {
-            w = std::cmp::max(w, bb.max.x);
+            h = std::cmp::max(h, bb.max.y);
         }
     }
-    w
+    h
 }
 
-pub fn text_size_multiline(
-    lines: &Vec<String>,
+/// Returns the size of columns laid out right-to-left, `line_spacing` is the spacing
+/// between columns.
+pub fn text_size_vertical_multiline(
+    lines: &[String],
     font: &SuperFont,
     scale: rusttype::Scale,
     line_spacing: f32,
 ) -> (i32, i32) {
-    let mut width = 0;
+    let mut height = 0;
 
     for line in lines {
-        width = width.max(text_size(scale, font, line).0);
+        height = height.max(text_height_vertical(scale, font, line));
     }
 
     (
-        width,
-        ((lines.len() as f32 * scale.y * line_spacing) - (line_spacing - 1.0) * scale.y).round()
+        ((lines.len() as f32 * scale.x * line_spacing) - (line_spacing - 1.0) * scale.x).round()
             as i32,
+        height,
     )
 }
 
@@ -50,29 +135,50 @@ pub fn parsed_text_size_with_emojis(
     scale: rusttype::Scale,
     font: &SuperFont,
     text: &str,
+    options: &LayoutOptions,
 ) -> (i32, i32) {
-    let v_metrics = font.main.v_metrics(scale);
+    TextLayout::parsed_with_emojis(text, &[], &mut 0, font, scale, options).size()
+}
 
-    let (mut w, mut h) = (0, 0);
+#[cfg(feature = "emoji")]
+/// assumes that emojis were parsed out
+pub fn parsed_text_metrics_with_emojis(
+    scale: rusttype::Scale,
+    font: &SuperFont,
+    text: &str,
+    options: &LayoutOptions,
+) -> TextMetrics {
+    TextLayout::parsed_with_emojis(text, &[], &mut 0, font, scale, options).metrics()
+}
 
-    for g in font.layout_with_emojis(
-        text,
-        &[],
-        &mut 0,
+#[cfg(feature = "emoji")]
+pub fn text_metrics_with_emojis(
+    scale: rusttype::Scale,
+    font: &SuperFont,
+    text: &str,
+    options: &LayoutOptions,
+) -> TextMetrics {
+    parsed_text_metrics_with_emojis(
         scale,
-        rusttype::point(0.0, v_metrics.ascent),
-    ) {
-        if let Some(bb) = g.0.pixel_bounding_box() {
-            w = std::cmp::max(w, bb.max.x);
-            h = std::cmp::max(h, bb.max.y);
-        }
-    }
-    (w, h)
+        font,
+        &crate::emoji::parse::clean_emojis(text),
+        options,
+    )
 }
 
 #[cfg(feature = "emoji")]
-pub fn text_size_with_emojis(scale: rusttype::Scale, font: &SuperFont, text: &str) -> (i32, i32) {
-    parsed_text_size_with_emojis(scale, font, &crate::emoji::parse::clean_emojis(text))
+pub fn text_size_with_emojis(
+    scale: rusttype::Scale,
+    font: &SuperFont,
+    text: &str,
+    options: &LayoutOptions,
+) -> (i32, i32) {
+    parsed_text_size_with_emojis(
+        scale,
+        font,
+        &crate::emoji::parse::clean_emojis(text),
+        options,
+    )
 }
 
 #[cfg(feature = "emoji")]
@@ -81,7 +187,7 @@ pub fn text_size_with_emojis(scale: rusttype::Scale, font: &SuperFont, text: &st
 /// assumes that emojis were parsed out
 pub fn parsed_text_width_with_emojis(scale: rusttype::Scale, font: &SuperFont, text: &str) -> i32 {
     let mut w = 0;
-    for g in font.layout_with_emojis(&text, &[], &mut 0, scale, rusttype::point(0.0, 0.0)) {
+    for g in font.layout_with_emojis(text, &[], &mut 0, scale, rusttype::point(0.0, 0.0)) {
         if let Some(bb) = g.0.pixel_bounding_box() {
             w = std::cmp::max(w, bb.max.x);
         }
@@ -99,40 +205,100 @@ pub fn text_width_with_emojis(scale: rusttype::Scale, font: &SuperFont, text: &s
 ///
 /// assumes that emojis were parsed out
 pub fn parsed_text_size_multiline_with_emojis(
-    lines: &Vec<String>,
+    lines: &[String],
     font: &SuperFont,
     scale: rusttype::Scale,
     line_spacing: f32,
+    options: &LayoutOptions,
 ) -> (i32, i32) {
-    let mut width = 0;
-
-    for line in lines {
-        width = width.max(parsed_text_size_with_emojis(scale, font, line).0);
-    }
-
-    (
-        width,
-        ((lines.len() as f32 * scale.y * line_spacing) - (line_spacing - 1.0) * scale.y).round()
-            as i32,
+    TextLayout::parsed_multiline_with_emojis(
+        lines,
+        &[],
+        &mut 0,
+        font,
+        scale,
+        line_spacing,
+        TextAlign::Start,
+        None,
+        options,
     )
+    .size()
 }
 
 #[cfg(feature = "emoji")]
 pub fn text_size_multiline_with_emojis(
-    lines: &Vec<String>,
+    lines: &[String],
     font: &SuperFont,
     scale: rusttype::Scale,
     line_spacing: f32,
+    options: &LayoutOptions,
 ) -> (i32, i32) {
-    let mut width = 0;
+    TextLayout::multiline_with_emojis(
+        lines,
+        font,
+        scale,
+        line_spacing,
+        TextAlign::Start,
+        None,
+        options,
+    )
+    .size()
+}
 
-    for line in lines {
-        width = width.max(text_size_with_emojis(scale, font, line).0);
-    }
+#[cfg(feature = "emoji")]
+#[test]
+fn spacing_test() {
+    use crate::superfont::Spacing;
 
-    (
-        width,
-        ((lines.len() as f32 * scale.y * line_spacing) - (line_spacing - 1.0) * scale.y).round()
-            as i32,
-    )
+    let font = SuperFont::new(crate::emoji::EMOJI_FONT.clone(), vec![]);
+    let scale = rusttype::Scale::uniform(20.0);
+
+    let w = text_width(scale, &font, "11");
+    let spaced = font.clone().with_letter_spacing(Spacing::Px(10.0));
+    assert_eq!(text_width(scale, &spaced, "11"), w + 10);
+
+    let w = text_width(scale, &font, "1 1");
+    let spaced = spaced.with_word_spacing(Spacing::Em(0.5));
+    assert_eq!(text_width(scale, &spaced, "1 1"), w + 10 * 2 + 10);
+}
+
+#[cfg(feature = "emoji")]
+#[test]
+fn text_metrics_test() {
+    use crate::outliner::AnchorMode;
+
+    let font = SuperFont::new(crate::emoji::EMOJI_FONT.clone(), vec![]);
+    let scale = rusttype::Scale::uniform(20.0);
+
+    let m = text_metrics(scale, &font, "1", &LayoutOptions::default());
+    let spaced = text_metrics(scale, &font, "1 ", &LayoutOptions::default());
+    assert!(spaced.advance > m.advance);
+    assert_eq!(spaced.ink_bounds, m.ink_bounds);
+    assert_eq!(m.logical_bounds.min.y, -m.ascent);
+    assert_eq!(m.logical_bounds.max.x, m.advance);
+
+    let options = LayoutOptions::new().with_anchor_mode(AnchorMode::Metrics);
+    let size = |text| TextLayout::new(text, &font, scale, &options).size();
+    let (w, h) = size("1 ");
+    assert_eq!(w, spaced.advance.round() as i32);
+    assert_eq!(h, size("#").1);
+    assert_eq!(h, (m.ascent - m.descent).round() as i32);
+}
+
+#[test]
+fn tab_stops_test() {
+    use crate::superfont::Spacing;
+    use crate::tabs::TabStops;
+
+    let font = SuperFont::new(crate::superfont::static_test_font(), vec![]);
+    let scale = rusttype::Scale::uniform(20.0);
+    let options = LayoutOptions::new().with_tab_stops(TabStops::every(Spacing::Px(200.0)));
+
+    let w = text_width_with_options(scale, &font, "\t1", &options);
+    assert!(w > 200);
+    assert!(text_width(scale, &font, "\t1") < 200);
+    let (sized, _) = text_size(scale, &font, "\t1", &options);
+    assert!(sized > 200);
+    let (multiline, _) = text_size_multiline(&["\t1".to_string()], &font, scale, 1.0, &options);
+    assert_eq!(multiline, sized);
 }
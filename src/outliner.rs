@@ -1,12 +1,83 @@
 use tiny_skia::PathBuilder;
 
-use crate::{measure::text_size, superfont::SuperFont};
+use crate::{
+    layout::{LayoutOptions, TextLayout, VerticalGlyph},
+    measure::text_size_vertical,
+    superfont::{SuperFont, SyntheticStyle},
+};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default)]
 pub enum TextAlign {
     Left,
     Center,
     Right,
+    /// Left for left-to-right lines, right for right-to-left lines.
+    #[default]
+    Start,
+    /// Right for left-to-right lines, left for right-to-left lines.
+    End,
+    /// Stretches every line but the last to the full width, by widening the gaps between
+    /// words (and between CJK characters with `LayoutOptions::justify_cjk`). The last line
+    /// is aligned like `Start`.
+    Justify,
+    /// Same as `Justify`, and stretches the last line too.
+    JustifyAll,
+}
+
+impl TextAlign {
+    /// Returns the horizontal anchor (0.0 left, 0.5 center, 1.0 right) of a line with
+    /// the given direction. Justified lines are anchored at their start.
+    pub fn anchor(&self, rtl: bool) -> f32 {
+        match (self, rtl) {
+            (TextAlign::Left, _)
+            | (TextAlign::Start | TextAlign::Justify | TextAlign::JustifyAll, false)
+            | (TextAlign::End, true) => 0.0,
+            (TextAlign::Center, _) => 0.5,
+            (TextAlign::Right, _)
+            | (TextAlign::Start | TextAlign::Justify | TextAlign::JustifyAll, true)
+            | (TextAlign::End, false) => 1.0,
+        }
+    }
+
+    /// Whether the line `index` of `count` lines is stretched to the full width.
+    pub(crate) fn justifies(&self, index: usize, count: usize) -> bool {
+        match self {
+            TextAlign::Justify => index + 1 < count,
+            TextAlign::JustifyAll => true,
+            _ => false,
+        }
+    }
+}
+
+/// Lines drawn with the text, at the positions and with the thickness the main font sets
+/// for them. They go across the whole line, over fallback glyphs and emojis too, vertical
+/// text isn't decorated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TextDecoration {
+    pub underline: bool,
+    pub strikethrough: bool,
+    /// at the ascent of the font
+    pub overline: bool,
+    /// interrupts underlines and overlines where glyphs cross them (descenders...)
+    pub skip_ink: bool,
+}
+
+impl TextDecoration {
+    pub fn is_none(&self) -> bool {
+        !(self.underline || self.strikethrough || self.overline)
+    }
+}
+
+/// What anchoring and alignment measure text by.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum AnchorMode {
+    /// The inked pixels of single lines (so "ace" is shorter than "Agy" and trailing
+    /// spaces don't count), and the line height for multiple lines.
+    #[default]
+    Ink,
+    /// The font metrics: the advance width and the ascent and descent of the main font,
+    /// so labels with different text line up.
+    Metrics,
 }
 
 #[cfg(feature = "emoji")]
@@ -19,24 +90,151 @@ pub struct PositionedEmoji<'a> {
     pub fallback: rusttype::PositionedGlyph<'a>,
 }
 
+// how far synthetic bold spreads glyphs, relative to the font size
+const SYNTHETIC_BOLD: f32 = 1.0 / 24.0;
+// the slant of synthetic italics, tan(12°) like the `slnt` markup sets on variable fonts
+const SYNTHETIC_SKEW: f32 = 0.2126;
+
 pub struct TextDrawer<'a> {
     pub pb: &'a mut PathBuilder,
     offset: rusttype::Point<f32>,
+    sideways: bool,
+    // horizontal shift per pixel above the baseline
+    skew: f32,
 }
 impl<'a> TextDrawer<'a> {
     pub fn new(pb: &'a mut PathBuilder) -> Self {
         Self {
             pb,
             offset: rusttype::Point { x: 0.0, y: 0.0 },
+            sideways: false,
+            skew: 0.0,
         }
     }
 
     #[inline]
     pub fn draw_glyph(&mut self, glyph: &rusttype::PositionedGlyph<'_>) {
         self.offset = glyph.position();
+        self.sideways = false;
         glyph.unpositioned().build_outline(self);
     }
 
+    #[inline]
+    pub fn draw_vertical_glyph(&mut self, glyph: &VerticalGlyph<'_>) {
+        self.offset = glyph.glyph.position();
+        self.sideways = glyph.sideways;
+        glyph.glyph.unpositioned().build_outline(self);
+        self.sideways = false;
+    }
+
+    #[inline]
+    fn draw_glyph_at(
+        &mut self,
+        glyph: &rusttype::PositionedGlyph<'_>,
+        x: f32,
+        y: f32,
+        synthetic: SyntheticStyle,
+    ) {
+        let position = glyph.position();
+        self.sideways = false;
+        self.skew = if synthetic.italic {
+            SYNTHETIC_SKEW
+        } else {
+            0.0
+        };
+
+        // bold copies are at most half a pixel apart, so that they merge
+        let spread = if synthetic.bold {
+            glyph.scale().y * SYNTHETIC_BOLD
+        } else {
+            0.0
+        };
+        let copies = (spread * 2.0).ceil() as usize;
+
+        for i in 0..=copies {
+            let dx = match copies {
+                0 => 0.0,
+                _ => spread * (i as f32 / copies as f32 - 0.5),
+            };
+            self.offset = rusttype::point(position.x + x + dx, position.y + y);
+            glyph.unpositioned().build_outline(self);
+        }
+        self.skew = 0.0;
+    }
+
+    /// Draws a laid out text with the top left corner of the layout at `x`, `y`.
+    pub fn draw_layout(&mut self, layout: &TextLayout<'_>, x: f32, y: f32) {
+        for g in layout.glyphs() {
+            if g.glyph.pixel_bounding_box().is_some() {
+                self.draw_glyph_at(&g.glyph, x, y, layout.font.synthetic);
+            }
+        }
+
+        self.draw_decorations(layout, x, y);
+    }
+
+    // adds the decorations of a layout to the path. Where they cross a glyph
+    // (strikethroughs) they are wound like its outline, so that the nonzero rule doesn't
+    // cut the glyph out of them
+    fn draw_decorations(&mut self, layout: &TextLayout<'_>, x: f32, y: f32) {
+        let rects = layout.decorations();
+        if rects.is_empty() {
+            return;
+        }
+
+        // the extent of the glyphs and whether they wind clockwise, found when a
+        // decoration crosses them
+        let mut glyphs = layout
+            .glyphs()
+            .filter_map(|g| Some((g.glyph.pixel_bounding_box()?, &g.glyph, None)))
+            .collect::<Vec<_>>();
+        glyphs.sort_by_key(|(bb, _, _)| bb.min.x);
+
+        for rect in rects {
+            let mut x0 = rect.min.x;
+
+            for (bb, glyph, positive) in glyphs.iter_mut() {
+                let (c0, c1) = (bb.min.x as f32, bb.max.x as f32);
+                if c1 <= x0
+                    || c0 >= rect.max.x
+                    || bb.max.y as f32 <= rect.min.y
+                    || bb.min.y as f32 >= rect.max.y
+                {
+                    continue;
+                }
+
+                let positive =
+                    *positive.get_or_insert_with(|| signed_area(&glyph_contours(glyph)) >= 0.0);
+
+                let (c0, c1) = (c0.max(x0), c1.min(rect.max.x));
+                self.draw_rect(x + x0, y + rect.min.y, x + c0, y + rect.max.y, true);
+                self.draw_rect(x + c0, y + rect.min.y, x + c1, y + rect.max.y, positive);
+                x0 = c1;
+            }
+
+            self.draw_rect(x + x0, y + rect.min.y, x + rect.max.x, y + rect.max.y, true);
+        }
+    }
+
+    // clockwise on screen when `positive`
+    fn draw_rect(&mut self, x0: f32, y0: f32, x1: f32, y1: f32, positive: bool) {
+        if x1 <= x0 {
+            return;
+        }
+
+        self.pb.move_to(x0, y0);
+        if positive {
+            self.pb.line_to(x1, y0);
+            self.pb.line_to(x1, y1);
+            self.pb.line_to(x0, y1);
+        } else {
+            self.pb.line_to(x0, y1);
+            self.pb.line_to(x1, y1);
+            self.pb.line_to(x1, y0);
+        }
+        self.pb.close();
+    }
+
     pub fn draw_text(
         &mut self,
         text: &str,
@@ -45,14 +243,14 @@ impl<'a> TextDrawer<'a> {
         font: &SuperFont,
         scale: rusttype::Scale,
     ) {
-        let v_metrics = font.main.v_metrics(scale);
-        for g in font.layout(text, scale, rusttype::point(x, y + v_metrics.ascent)) {
-            if let Some(_) = g.pixel_bounding_box() {
-                self.draw_glyph(&g);
-            }
-        }
+        self.draw_layout(
+            &TextLayout::new(text, font, scale, &LayoutOptions::default()),
+            x,
+            y,
+        );
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn draw_text_anchored(
         &mut self,
         text: &str,
@@ -62,17 +260,18 @@ impl<'a> TextDrawer<'a> {
         ay: f32,
         font: &SuperFont,
         scale: rusttype::Scale,
+        options: &LayoutOptions,
     ) {
-        let (w, h) = text_size(scale, font, text);
-        let x = x - w as f32 * ax;
-        let y = y - h as f32 * ay;
+        let layout = TextLayout::new(text, font, scale, options);
+        let (w, h) = layout.size();
 
-        self.draw_text(text, x, y, font, scale);
+        self.draw_layout(&layout, x - w as f32 * ax, y - h as f32 * ay);
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn draw_text_multiline(
         &mut self,
-        lines: &Vec<String>,
+        lines: &[String],
         x: f32,
         y: f32,
         ax: f32,
@@ -82,32 +281,133 @@ impl<'a> TextDrawer<'a> {
         scale: rusttype::Scale,
         line_spacing: f32,
         align: TextAlign,
+        options: &LayoutOptions,
     ) {
-        let h = (lines.len() as f32 * (scale.y * line_spacing)) - (line_spacing - 1.0) * scale.y;
+        let layout = TextLayout::multiline(
+            lines,
+            font,
+            scale,
+            line_spacing,
+            align,
+            Some(width),
+            options,
+        );
 
-        let mut x = x - width * ax;
-        let mut y = y - h * ay;
+        self.draw_layout(&layout, x - width * ax, y - layout.height * ay);
+    }
 
-        let ax = match align {
-            TextAlign::Left => 0.0,
-            TextAlign::Center => {
-                x += width * 0.5;
-                0.5
-            }
-            TextAlign::Right => {
-                x += width;
-                1.0
+    /// Draws `text` as a vertical column with its top left corner at `x`, `y`.
+    /// Tabs aren't moved to tab stops in vertical text.
+    pub fn draw_text_vertical(
+        &mut self,
+        text: &str,
+        x: f32,
+        y: f32,
+        font: &SuperFont,
+        scale: rusttype::Scale,
+    ) {
+        for g in font.layout_vertical(text, scale, rusttype::point(x + scale.x / 2.0, y)) {
+            if g.glyph.pixel_bounding_box().is_some() {
+                self.draw_vertical_glyph(&g);
             }
-        };
+        }
+    }
 
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_text_vertical_anchored(
+        &mut self,
+        text: &str,
+        x: f32,
+        y: f32,
+        ax: f32,
+        ay: f32,
+        font: &SuperFont,
+        scale: rusttype::Scale,
+    ) {
+        let (w, h) = text_size_vertical(scale, font, text);
+        let x = x - w as f32 * ax;
+        let y = y - h as f32 * ay;
+
+        self.draw_text_vertical(text, x, y, font, scale);
+    }
+
+    /// Draws each line as a vertical column, columns go from right to left and `align`
+    /// positions each column within `height` (left / start is the top).
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_text_vertical_multiline(
+        &mut self,
+        lines: &[String],
+        x: f32,
+        y: f32,
+        ax: f32,
+        ay: f32,
+        height: f32,
+        font: &SuperFont,
+        scale: rusttype::Scale,
+        line_spacing: f32,
+        align: TextAlign,
+    ) {
+        let w = (lines.len() as f32 * (scale.x * line_spacing)) - (line_spacing - 1.0) * scale.x;
+
+        let mut x = x - w * ax + w - scale.x;
+        let y = y - height * ay;
+
+        let ay = align.anchor(false);
         for line in lines {
-            self.draw_text_anchored(line, x, y, ax, 0.0, font, scale);
-            y += scale.y * line_spacing;
+            self.draw_text_vertical_anchored(line, x, y + height * ay, 0.0, ay, font, scale);
+            x -= scale.x * line_spacing;
         }
     }
 }
 #[cfg(feature = "emoji")]
 impl<'a> TextDrawer<'a> {
+    /// Draws a laid out text with the top left corner of the layout at `x`, `y`, the
+    /// emojis of the layout are pushed to `emoji_acc` instead of being drawn.
+    pub fn draw_layout_with_emojis<'f>(
+        &mut self,
+        layout: &TextLayout<'f>,
+        x: f32,
+        y: f32,
+
+        emoji_acc: &mut Vec<PositionedEmoji<'f>>,
+    ) {
+        let shift = layout.font.emoji_options.shift;
+
+        for g in layout.glyphs() {
+            if g.glyph.pixel_bounding_box().is_none() {
+                continue;
+            }
+
+            let Some(emoji) = g.emoji else {
+                self.draw_glyph_at(&g.glyph, x, y, layout.font.synthetic);
+                continue;
+            };
+
+            let mut fallback = g.glyph.clone();
+            let position = fallback.position();
+            fallback.set_position(rusttype::point(position.x + x, position.y + y));
+
+            if let Some(bb) = fallback.pixel_bounding_box() {
+                let w = bb.width();
+                let w2 = (w as f32 / 2.0).round() as i32;
+
+                emoji_acc.push(PositionedEmoji {
+                    position: (
+                        (bb.min.x + w2) as i64 + shift.0,
+                        (bb.min.y + w2) as i64 + shift.1,
+                    ),
+                    size: w as u32,
+                    emoji,
+
+                    fallback,
+                });
+            }
+        }
+
+        self.draw_decorations(layout, x, y);
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn draw_text_with_emojis<'f>(
         &mut self,
         text: &str, // assumes that text is parsed
@@ -120,41 +420,18 @@ impl<'a> TextDrawer<'a> {
 
         emoji_acc: &mut Vec<PositionedEmoji<'f>>,
     ) {
-        let v_metrics = font.main.v_metrics(scale);
-        for (g, emoji) in font.layout_with_emojis(
+        let layout = TextLayout::parsed_with_emojis(
             text,
             emojis,
             emoji_idx,
+            font,
             scale,
-            rusttype::point(x, y + v_metrics.ascent),
-        ) {
-            if let Some(bb) = g.pixel_bounding_box() {
-                match emoji {
-                    Some(emoji) => {
-                        let w = bb.width();
-                        let w2 = (w as f32 / 2.0).round() as i32;
-
-                        let position = (bb.min.x + w2, bb.min.y + w2);
-
-                        emoji_acc.push(PositionedEmoji {
-                            position: (
-                                position.0 as i64 + font.emoji_options.shift.0,
-                                position.1 as i64 + font.emoji_options.shift.1,
-                            ),
-                            size: w as u32,
-                            emoji: *emoji,
-
-                            fallback: g,
-                        });
-                    }
-                    None => {
-                        self.draw_glyph(&g);
-                    }
-                }
-            }
-        }
+            &LayoutOptions::default(),
+        );
+        self.draw_layout_with_emojis(&layout, x, y, emoji_acc);
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn draw_text_anchored_with_emojis<'f>(
         &mut self,
         text: &str, // assumes that text is parsed
@@ -166,19 +443,20 @@ impl<'a> TextDrawer<'a> {
         ay: f32,
         font: &'f SuperFont,
         scale: rusttype::Scale,
+        options: &LayoutOptions,
 
         emoji_acc: &mut Vec<PositionedEmoji<'f>>,
     ) {
-        let (w, h) = crate::measure::parsed_text_size_with_emojis(scale, font, text);
-        let x = x - w as f32 * ax;
-        let y = y - h as f32 * ay;
+        let layout = TextLayout::parsed_with_emojis(text, emojis, emoji_idx, font, scale, options);
+        let (w, h) = layout.size();
 
-        self.draw_text_with_emojis(text, emojis, emoji_idx, x, y, font, scale, emoji_acc);
+        self.draw_layout_with_emojis(&layout, x - w as f32 * ax, y - h as f32 * ay, emoji_acc);
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn draw_text_multiline_with_emojis<'f>(
         &mut self,
-        lines: &Vec<String>,
+        lines: &[String],
         emojis: &[crate::emoji::source::EmojiType],
         emoji_idx: &mut usize,
         x: f32,
@@ -190,65 +468,209 @@ impl<'a> TextDrawer<'a> {
         scale: rusttype::Scale,
         line_spacing: f32,
         align: TextAlign,
+        options: &LayoutOptions,
 
         emoji_acc: &mut Vec<PositionedEmoji<'f>>,
     ) {
-        let h = (lines.len() as f32 * (scale.y * line_spacing)) - (line_spacing - 1.0) * scale.y;
+        let layout = TextLayout::parsed_multiline_with_emojis(
+            lines,
+            emojis,
+            emoji_idx,
+            font,
+            scale,
+            line_spacing,
+            align,
+            Some(width),
+            options,
+        );
+
+        self.draw_layout_with_emojis(&layout, x - width * ax, y - layout.height * ay, emoji_acc);
+    }
+}
+
+impl TextDrawer<'_> {
+    // maps a glyph space point into the path, slanting synthetic italics and rotating
+    // sideways glyphs 90° clockwise
+    #[inline]
+    fn map(&self, x: f32, y: f32) -> (f32, f32) {
+        let x = x - y * self.skew;
+        if self.sideways {
+            (self.offset.x - y, self.offset.y + x)
+        } else {
+            (self.offset.x + x, self.offset.y + y)
+        }
+    }
+}
+
+// flattens the outline of a positioned glyph into polygons
+pub(crate) fn glyph_contours(glyph: &rusttype::PositionedGlyph) -> Vec<Vec<rusttype::Point<f32>>> {
+    const STEPS: usize = 8;
 
-        let mut x = x - width * ax;
-        let mut y = y - h * ay;
+    struct Flattener {
+        offset: rusttype::Point<f32>,
+        contours: Vec<Vec<rusttype::Point<f32>>>,
+    }
+
+    impl Flattener {
+        fn last(&self) -> rusttype::Point<f32> {
+            self.contours
+                .last()
+                .and_then(|c| c.last())
+                .copied()
+                .unwrap_or(self.offset)
+        }
 
-        let ax = match align {
-            TextAlign::Left => 0.0,
-            TextAlign::Center => {
-                x += width * 0.5;
-                0.5
+        fn push(&mut self, p: rusttype::Point<f32>) {
+            if let Some(contour) = self.contours.last_mut() {
+                contour.push(p);
             }
-            TextAlign::Right => {
-                x += width;
-                1.0
+        }
+    }
+
+    impl rusttype::OutlineBuilder for Flattener {
+        fn move_to(&mut self, x: f32, y: f32) {
+            self.contours
+                .push(vec![self.offset + rusttype::vector(x, y)]);
+        }
+
+        fn line_to(&mut self, x: f32, y: f32) {
+            self.push(self.offset + rusttype::vector(x, y));
+        }
+
+        fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+            let (p0, p1) = (self.last(), self.offset + rusttype::vector(x1, y1));
+            let p2 = self.offset + rusttype::vector(x, y);
+
+            for i in 1..=STEPS {
+                let t = i as f32 / STEPS as f32;
+                let u = 1.0 - t;
+                let (a, b, c) = (u * u, 2.0 * u * t, t * t);
+                self.push(rusttype::point(
+                    a * p0.x + b * p1.x + c * p2.x,
+                    a * p0.y + b * p1.y + c * p2.y,
+                ));
             }
-        };
+        }
 
-        for line in lines {
-            self.draw_text_anchored_with_emojis(
-                line, emojis, emoji_idx, x, y, ax, 0.0, font, scale, emoji_acc,
+        fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+            let (p0, p1) = (self.last(), self.offset + rusttype::vector(x1, y1));
+            let (p2, p3) = (
+                self.offset + rusttype::vector(x2, y2),
+                self.offset + rusttype::vector(x, y),
             );
-            y += scale.y * line_spacing;
+
+            for i in 1..=STEPS {
+                let t = i as f32 / STEPS as f32;
+                let u = 1.0 - t;
+                let (a, b, c, d) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+                self.push(rusttype::point(
+                    a * p0.x + b * p1.x + c * p2.x + d * p3.x,
+                    a * p0.y + b * p1.y + c * p2.y + d * p3.y,
+                ));
+            }
         }
+
+        fn close(&mut self) {}
     }
+
+    let mut flattener = Flattener {
+        offset: glyph.position(),
+        contours: Vec::new(),
+    };
+    glyph.unpositioned().build_outline(&mut flattener);
+    flattener.contours
+}
+
+// twice the signed area of polygons, positive when they go clockwise on screen
+fn signed_area(contours: &[Vec<rusttype::Point<f32>>]) -> f32 {
+    contours
+        .iter()
+        .flat_map(|contour| contour.iter().zip(contour.iter().cycle().skip(1)))
+        .map(|(a, b)| a.x * b.y - b.x * a.y)
+        .sum()
 }
 
 impl rusttype::OutlineBuilder for TextDrawer<'_> {
     fn move_to(&mut self, x: f32, y: f32) {
-        self.pb.move_to(x + self.offset.x, y + self.offset.y);
+        let (x, y) = self.map(x, y);
+        self.pb.move_to(x, y);
     }
 
     fn line_to(&mut self, x: f32, y: f32) {
-        self.pb.line_to(x + self.offset.x, y + self.offset.y);
+        let (x, y) = self.map(x, y);
+        self.pb.line_to(x, y);
     }
 
     fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
-        self.pb.quad_to(
-            x1 + self.offset.x,
-            y1 + self.offset.y,
-            x + self.offset.x,
-            y + self.offset.y,
-        );
+        let (x1, y1) = self.map(x1, y1);
+        let (x, y) = self.map(x, y);
+        self.pb.quad_to(x1, y1, x, y);
     }
 
     fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
-        self.pb.cubic_to(
-            x1 + self.offset.x,
-            y1 + self.offset.y,
-            x2 + self.offset.x,
-            y2 + self.offset.y,
-            x + self.offset.x,
-            y + self.offset.y,
-        );
+        let (x1, y1) = self.map(x1, y1);
+        let (x2, y2) = self.map(x2, y2);
+        let (x, y) = self.map(x, y);
+        self.pb.cubic_to(x1, y1, x2, y2, x, y);
     }
 
     fn close(&mut self) {
         self.pb.close();
     }
 }
+
+#[test]
+fn decoration_winding_test() {
+    use crate::drawing::{outline::Outline, text::draw_text_layout};
+    use crate::superfont::{static_test_font, test_font};
+
+    // the "Ṫ" comes from the truetype fallback, its outline winds the other way than the
+    // ones of the main CFF font
+    let font = SuperFont::new(test_font(), vec![static_test_font()]);
+    let scale = rusttype::Scale::uniform(60.0);
+    let text = "oṪoṪ";
+
+    let layout = TextLayout::new(text, &font, scale, &LayoutOptions::default());
+    let windings = layout
+        .glyphs()
+        .map(|g| signed_area(&glyph_contours(&g.glyph)) >= 0.0)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        windings,
+        vec![windings[0], !windings[0], windings[0], !windings[0]]
+    );
+
+    let draw = |decoration| {
+        let options = LayoutOptions::new().with_decoration(decoration);
+        let layout = TextLayout::new(text, &font, scale, &options);
+        let mut image = image::RgbaImage::new(layout.width as u32 + 20, 100);
+        draw_text_layout(
+            &mut image,
+            &tiny_skia::Paint::default(),
+            Outline::None,
+            10.0,
+            10.0,
+            0.0,
+            0.0,
+            &layout,
+        )
+        .unwrap();
+        image
+    };
+
+    // the strikethrough doesn't cut holes in the glyphs of either font
+    let plain = draw(TextDecoration::default());
+    let struck = draw(TextDecoration {
+        strikethrough: true,
+        underline: true,
+        ..Default::default()
+    });
+    assert!(plain
+        .pixels()
+        .zip(struck.pixels())
+        .all(|(plain, struck)| struck[3] >= plain[3]));
+    assert!(plain
+        .pixels()
+        .zip(struck.pixels())
+        .any(|(plain, struck)| struck[3] > plain[3]));
+}
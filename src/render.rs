@@ -50,11 +50,11 @@ pub(crate) fn render_path(
             stroke,
             fill: stroke_fill,
         } => with_pixmap(image, |pixmap| {
-            pixmap.stroke_path(&path, &stroke_fill, &stroke, Transform::identity(), None);
-            pixmap.fill_path(&path, &fill, FillRule::Winding, Transform::identity(), None);
+            pixmap.stroke_path(path, stroke_fill, stroke, Transform::identity(), None);
+            pixmap.fill_path(path, fill, FillRule::Winding, Transform::identity(), None);
         })?,
         Outline::None => with_pixmap(image, |pixmap| {
-            pixmap.fill_path(&path, &fill, FillRule::Winding, Transform::identity(), None)
+            pixmap.fill_path(path, fill, FillRule::Winding, Transform::identity(), None)
         })?,
     }
 
@@ -120,7 +120,7 @@ pub(crate) fn render_text_emoji_fn<'a, R: EmojiResolver>(
         let mut emojis = Vec::with_capacity(emoji_count);
         f(&mut td, &mut emojis);
 
-        let emojis = resolve_emoji_ims(&mut td, &emojis, &font, &mut emoji_resolver);
+        let emojis = resolve_emoji_ims(&mut td, &emojis, font, &mut emoji_resolver);
 
         if pb.is_empty() {
             // if there is no text, just render the emojis
@@ -150,11 +150,11 @@ pub(crate) fn render_path_and_emojis(
             stroke,
             fill: stroke_fill,
         } => with_pixmap(image, |pixmap| {
-            pixmap.stroke_path(&path, &stroke_fill, &stroke, Transform::identity(), None);
-            pixmap.fill_path(&path, &fill, FillRule::Winding, Transform::identity(), None);
+            pixmap.stroke_path(path, stroke_fill, stroke, Transform::identity(), None);
+            pixmap.fill_path(path, fill, FillRule::Winding, Transform::identity(), None);
         })?,
         Outline::None => with_pixmap(image, |pixmap| {
-            pixmap.fill_path(&path, &fill, FillRule::Winding, Transform::identity(), None)
+            pixmap.fill_path(path, fill, FillRule::Winding, Transform::identity(), None)
         })?,
     }
 
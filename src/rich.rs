@@ -0,0 +1,594 @@
+use tiny_skia::Paint;
+use unicode_bidi::{Level, ParagraphBidiInfo};
+use unicode_linebreak::BreakOpportunity;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{
+    drawing::outline::Outline,
+    layout::{LayoutOptions, TextLayout},
+    outliner::{AnchorMode, TextAlign},
+    shaping::base_direction_rtl,
+    superfont::SuperFont,
+};
+
+/// A run of text drawn with its own font, scale, fill and outline (and the emoji options
+/// of its font). Spans are laid out one after the other as one paragraph, see
+/// [`RichLayout`].
+#[derive(Clone)]
+pub struct TextSpan<'a, 'font> {
+    pub text: &'a str,
+    pub font: &'a SuperFont<'font>,
+    pub scale: rusttype::Scale,
+    pub fill: &'a Paint<'a>,
+    pub outline: Outline<'a>,
+}
+
+impl<'a, 'font> TextSpan<'a, 'font> {
+    pub fn new(
+        text: &'a str,
+        font: &'a SuperFont<'font>,
+        scale: rusttype::Scale,
+        fill: &'a Paint<'a>,
+    ) -> Self {
+        Self {
+            text,
+            font,
+            scale,
+            fill,
+            outline: Outline::None,
+        }
+    }
+
+    pub fn with_outline(mut self, outline: Outline<'a>) -> Self {
+        self.outline = outline;
+        self
+    }
+
+    fn with_text(&self, text: &'a str) -> Self {
+        Self {
+            text,
+            ..self.clone()
+        }
+    }
+}
+
+/// Lines of spans laid out top to bottom, the spans of a line share a baseline below the
+/// tallest of them.
+pub struct RichLayout<'a, 'font> {
+    /// the width of the layout, used for anchoring
+    pub width: f32,
+    /// the height of the layout, used for anchoring
+    pub height: f32,
+
+    lines: Vec<RichLine<'a, 'font>>,
+}
+
+struct RichLine<'a, 'font> {
+    pieces: Vec<RichPiece<'a, 'font>>,
+    top: f32,
+    /// the largest `scale.y` of the spans
+    size: f32,
+    /// the largest ascent minus descent of the spans
+    extent: f32,
+    /// width of the line in pixels (up to the right edge of its last glyph)
+    width: f32,
+    advance: f32,
+    rtl: bool,
+}
+
+/// The part of a span on a line at one bidi embedding level, laid out on its own.
+pub(crate) struct RichPiece<'a, 'font> {
+    pub span: TextSpan<'a, 'font>,
+    /// top left corner of `layout` in the rich layout
+    pub x: f32,
+    pub y: f32,
+    pub layout: TextLayout<'font>,
+}
+
+impl<'a, 'font> RichLayout<'a, 'font> {
+    /// Lays lines of spans out, as returned by [`rich_text_wrap`]. `line_spacing` is a
+    /// multiple of the largest `scale.y` of each line. Lines are aligned within `width`, or
+    /// within the widest line when it's `None`, and justified lines are aligned at the
+    /// start. Lines are measured with the [`AnchorMode`] of `options`.
+    pub fn new(
+        lines: &[Vec<TextSpan<'a, 'font>>],
+        line_spacing: f32,
+        align: TextAlign,
+        width: Option<f32>,
+        options: &LayoutOptions,
+    ) -> Self {
+        layout(lines, line_spacing, align, width, options, false)
+    }
+
+    #[cfg(feature = "emoji")]
+    /// Same as [`RichLayout::new`], parsing emojis out of every span with the emoji options
+    /// of its font.
+    pub fn new_with_emojis(
+        lines: &[Vec<TextSpan<'a, 'font>>],
+        line_spacing: f32,
+        align: TextAlign,
+        width: Option<f32>,
+        options: &LayoutOptions,
+    ) -> Self {
+        layout(lines, line_spacing, align, width, options, true)
+    }
+
+    /// Returns the size of the layout rounded to pixels.
+    pub fn size(&self) -> (i32, i32) {
+        (self.width.round() as i32, self.height.round() as i32)
+    }
+
+    pub(crate) fn pieces(&self) -> impl Iterator<Item = &RichPiece<'a, 'font>> {
+        self.lines.iter().flat_map(|line| line.pieces.iter())
+    }
+}
+
+/// Wraps spans into lines no wider than `width`, breaking at the line break opportunities
+/// of their joined text and at its line breaks. Spans are cut where lines break, a word
+/// wider than `width` gets a line of its own. Lines are measured with `options`.
+pub fn rich_text_wrap<'a, 'font>(
+    spans: &[TextSpan<'a, 'font>],
+    width: i32,
+    options: &LayoutOptions,
+) -> Vec<Vec<TextSpan<'a, 'font>>> {
+    wrap(spans, width, options, false)
+}
+
+#[cfg(feature = "emoji")]
+/// Same as [`rich_text_wrap`], measuring with emojis.
+pub fn rich_text_wrap_with_emojis<'a, 'font>(
+    spans: &[TextSpan<'a, 'font>],
+    width: i32,
+    options: &LayoutOptions,
+) -> Vec<Vec<TextSpan<'a, 'font>>> {
+    wrap(spans, width, options, true)
+}
+
+/// Returns the size of lines of spans, as laid out by [`RichLayout::new`].
+pub fn rich_text_size(
+    lines: &[Vec<TextSpan>],
+    line_spacing: f32,
+    options: &LayoutOptions,
+) -> (i32, i32) {
+    RichLayout::new(lines, line_spacing, TextAlign::Start, None, options).size()
+}
+
+fn wrap<'a, 'font>(
+    spans: &[TextSpan<'a, 'font>],
+    width: i32,
+    options: &LayoutOptions,
+    emojis: bool,
+) -> Vec<Vec<TextSpan<'a, 'font>>> {
+    let text = spans.iter().map(|span| span.text).collect::<String>();
+    let width = width as f32;
+    // where spans end, the text of two spans is never shaped together
+    let ends = span_ends(spans);
+
+    // the line from `start` to `end`, without the spaces it ends with
+    let line = |start: usize, end: usize| {
+        slice(
+            spans,
+            &ends,
+            start,
+            start + text[start..end].trim_end().len(),
+        )
+    };
+    // the advance and ink width of the text from `start` to `end`
+    let measure = |start: usize, end: usize| {
+        if start == end {
+            return (0.0, 0.0);
+        }
+        let line = rich_line(
+            &slice(spans, &ends, start, end),
+            0.0,
+            false,
+            options,
+            emojis,
+        );
+        (line.advance, line.width)
+    };
+
+    // the kerning between the clusters that touch at `i`
+    let join = |i: usize| {
+        if ends.binary_search(&i).is_ok() {
+            return 0.0;
+        }
+        let (Some(before), Some(after)) = (
+            text[..i].graphemes(true).next_back(),
+            text[i..].graphemes(true).next(),
+        ) else {
+            return 0.0;
+        };
+
+        let (start, end) = (i - before.len(), i + after.len());
+        measure(start, end).0 - measure(start, i).0 - measure(i, end).0
+    };
+
+    let mut lines = Vec::new();
+    let (mut start, mut end) = (0, None);
+    // where the next segment starts on the line, and the right edge of the ink of the line
+    let (mut caret, mut ink) = (0.0, 0.0_f32);
+    let mut segment_start = 0;
+
+    // every segment between two break opportunities is measured once
+    for (i, opportunity) in unicode_linebreak::linebreaks(&text) {
+        let trimmed = segment_start + text[segment_start..i].trim_end().len();
+        let (advance, segment_ink) = measure(segment_start, trimmed);
+        let space = measure(trimmed, i).0;
+
+        let mut x = match end {
+            Some(_) => caret + join(segment_start),
+            None => 0.0,
+        };
+        let extent = |x: f32, ink: f32| match options.anchor_mode {
+            AnchorMode::Ink if segment_ink > 0.0 => ink.max(x + segment_ink),
+            AnchorMode::Ink => ink,
+            AnchorMode::Metrics => x + advance,
+        };
+
+        // advances added up segment by segment can be a rounding error off the advances
+        // of the laid out line
+        if let Some(e) = end {
+            if extent(x, ink) > width + 1e-3 {
+                lines.push(line(start, e));
+                start = e;
+                (x, ink) = (0.0, 0.0);
+            }
+        }
+        if segment_ink > 0.0 {
+            ink = ink.max(x + segment_ink);
+        }
+        caret = x + advance + space;
+        end = Some(i);
+        segment_start = i;
+
+        if opportunity == BreakOpportunity::Mandatory {
+            lines.push(line(start, i));
+            start = i;
+            end = None;
+            (caret, ink) = (0.0, 0.0);
+        }
+    }
+
+    lines
+}
+
+// the byte offsets in the joined text of the spans where each of them ends
+fn span_ends(spans: &[TextSpan]) -> Vec<usize> {
+    spans
+        .iter()
+        .scan(0, |offset, span| {
+            *offset += span.text.len();
+            Some(*offset)
+        })
+        .collect()
+}
+
+// the parts of the spans from `start` to `end`, byte offsets in their joined text, `ends`
+// being their `span_ends`. An empty line keeps the span it's in, for its height.
+fn slice<'a, 'font>(
+    spans: &[TextSpan<'a, 'font>],
+    ends: &[usize],
+    start: usize,
+    end: usize,
+) -> Vec<TextSpan<'a, 'font>> {
+    // the first span that ends after `start`
+    let first = ends.partition_point(|&e| e <= start);
+    let mut sliced = Vec::new();
+
+    for (span, &span_end) in spans.iter().zip(ends).skip(first) {
+        let offset = span_end - span.text.len();
+        if offset >= end {
+            break;
+        }
+
+        let (s, e) = (start.max(offset), end.min(span_end));
+        if s < e {
+            sliced.push(span.with_text(&span.text[s - offset..e - offset]));
+        }
+    }
+
+    if sliced.is_empty() {
+        sliced.extend(
+            spans
+                .get(first)
+                .or(spans.last())
+                .map(|span| span.with_text("")),
+        );
+    }
+    sliced
+}
+
+fn measure(line: &RichLine, anchor_mode: AnchorMode) -> f32 {
+    match anchor_mode {
+        AnchorMode::Ink => line.width,
+        AnchorMode::Metrics => line.advance,
+    }
+}
+
+fn layout<'a, 'font>(
+    lines: &[Vec<TextSpan<'a, 'font>>],
+    line_spacing: f32,
+    align: TextAlign,
+    width: Option<f32>,
+    options: &LayoutOptions,
+    emojis: bool,
+) -> RichLayout<'a, 'font> {
+    let anchor_mode = options.anchor_mode;
+
+    let (mut top, mut rtl) = (0.0, false);
+    let mut lines = lines
+        .iter()
+        .map(|spans| {
+            let line = rich_line(spans, top, rtl, options, emojis);
+            top += line.size * line_spacing;
+            rtl = line.rtl;
+            line
+        })
+        .collect::<Vec<_>>();
+
+    let width = width.unwrap_or_else(|| {
+        lines
+            .iter()
+            .fold(0.0, |w, l| measure(l, anchor_mode).max(w))
+    });
+
+    for line in lines.iter_mut() {
+        let ax = align.anchor(line.rtl);
+        let x = width * ax - measure(line, anchor_mode) * ax;
+
+        for piece in line.pieces.iter_mut() {
+            piece.x += x;
+        }
+    }
+
+    let height = lines.last().map_or(0.0, |last| match anchor_mode {
+        AnchorMode::Ink => last.top + last.size,
+        AnchorMode::Metrics => last.top + last.extent,
+    });
+
+    RichLayout {
+        width,
+        height,
+        lines,
+    }
+}
+
+// lays the spans of a line out from x = 0 on a shared baseline, in visual order: the parts
+// of the spans at every bidi embedding level of the joined text are laid out on their own
+// and reordered like the runs of a single text. A line without a strong character keeps
+// the direction `rtl` of the line before it.
+fn rich_line<'a, 'font>(
+    spans: &[TextSpan<'a, 'font>],
+    top: f32,
+    rtl: bool,
+    options: &LayoutOptions,
+    emojis: bool,
+) -> RichLine<'a, 'font> {
+    // the text of every span, with emojis replaced by placeholders when they are parsed
+    #[cfg(feature = "emoji")]
+    let parsed = spans
+        .iter()
+        .map(|span| match emojis {
+            true => crate::emoji::parse::parse_out_emojis(
+                span.text,
+                span.font.emoji_options.parse_shortcodes,
+                span.font.emoji_options.parse_discord_emojis,
+            ),
+            false => (span.text.to_string(), Vec::new()),
+        })
+        .collect::<Vec<_>>();
+    #[cfg(not(feature = "emoji"))]
+    let parsed = {
+        let _ = emojis;
+        spans
+            .iter()
+            .map(|span| (span.text.to_string(), ()))
+            .collect::<Vec<_>>()
+    };
+
+    let text = parsed
+        .iter()
+        .map(|(text, _)| text.as_str())
+        .collect::<String>();
+    let rtl = base_direction_rtl(&text).unwrap_or(rtl);
+    let base = if rtl { Level::rtl() } else { Level::ltr() };
+    let levels = ParagraphBidiInfo::new(&text, Some(base)).reordered_levels(0..text.len());
+
+    // (span, range in its text, level) of the parts of the spans at one level, an empty
+    // span keeps a part for its height
+    let mut runs = Vec::new();
+    let mut offset = 0;
+    for (i, (span_text, _)) in parsed.iter().enumerate() {
+        let level = |j: usize| levels.get(offset + j).copied().unwrap_or(base);
+        let mut start = 0;
+
+        for (j, _) in span_text.char_indices() {
+            if level(j) != level(start) {
+                runs.push((i, start..j, level(start)));
+                start = j;
+            }
+        }
+        runs.push((i, start..span_text.len(), level(start)));
+        offset += span_text.len();
+    }
+
+    let order = ParagraphBidiInfo::reorder_visual(&runs.iter().map(|r| r.2).collect::<Vec<_>>());
+    let layouts = order
+        .iter()
+        .map(|&r| {
+            let (i, range, level) = &runs[r];
+            let (span, (span_text, _span_emojis)) = (&spans[*i], &parsed[*i]);
+            let text = &span_text[range.clone()];
+
+            #[cfg(feature = "emoji")]
+            if emojis {
+                let mut emoji_idx = span_text[..range.start]
+                    .matches(crate::emoji::parse::PLACEHOLDER_EMOJI)
+                    .count();
+
+                return TextLayout::parsed_directed_with_emojis(
+                    text,
+                    level.is_rtl(),
+                    _span_emojis,
+                    &mut emoji_idx,
+                    span.font,
+                    span.scale,
+                    options,
+                );
+            }
+
+            TextLayout::directed(text, level.is_rtl(), span.font, span.scale, options)
+        })
+        .collect::<Vec<_>>();
+
+    // every layout has a single line, with its baseline at the ascent of its font
+    let ascent = layouts
+        .iter()
+        .map(|layout| layout.lines[0].baseline)
+        .fold(0.0, f32::max);
+
+    let mut caret = 0.0;
+    let mut width: f32 = 0.0;
+
+    let pieces = order
+        .iter()
+        .zip(layouts)
+        .map(|(&r, layout)| {
+            let line = &layout.lines[0];
+            if line.width > 0.0 {
+                width = width.max(caret + line.width);
+            }
+
+            let x = caret;
+            caret += line.advance;
+
+            RichPiece {
+                span: spans[runs[r].0].clone(),
+                x,
+                y: top + ascent - line.baseline,
+                layout,
+            }
+        })
+        .collect();
+
+    RichLine {
+        pieces,
+        top,
+        size: spans.iter().fold(0.0, |s, span| span.scale.y.max(s)),
+        extent: spans.iter().fold(0.0, |e, span| {
+            let v_metrics = span.font.main.v_metrics(span.scale);
+            (v_metrics.ascent - v_metrics.descent).max(e)
+        }),
+        width,
+        advance: caret,
+        rtl,
+    }
+}
+
+#[cfg(feature = "emoji")]
+#[test]
+fn rich_text_test() {
+    let font = SuperFont::new(crate::emoji::EMOJI_FONT.clone(), vec![]);
+    let (small, large) = (
+        rusttype::Scale::uniform(20.0),
+        rusttype::Scale::uniform(40.0),
+    );
+    let fill = Paint::default();
+    let options = LayoutOptions::default();
+
+    let spans = [
+        TextSpan::new("11 ", &font, large, &fill),
+        TextSpan::new("22 11 22", &font, small, &fill),
+    ];
+
+    let first = vec![spans[0].clone(), spans[1].with_text("22 11")];
+    let width = RichLayout::new(&[first], 1.0, TextAlign::Left, None, &options).width as i32;
+    let lines = rich_text_wrap(&spans, width, &options);
+
+    let texts = lines
+        .iter()
+        .map(|line| line.iter().map(|span| span.text).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    assert_eq!(texts, vec![vec!["11 ", "22 11"], vec!["22"]]);
+
+    // the small glyphs of the first line sit on the baseline of the large ones
+    let layout = RichLayout::new(&lines, 1.0, TextAlign::Left, None, &options);
+    let baselines = layout
+        .pieces()
+        .map(|piece| piece.y + piece.layout.lines[0].baseline)
+        .collect::<Vec<_>>();
+    assert_eq!(baselines[0], baselines[1]);
+    assert!(baselines[2] > baselines[1]);
+
+    // the second line goes down by the height of the large span, and ends with the small
+    assert_eq!(layout.height, 40.0 + 20.0);
+    assert!(layout.width >= width as f32 - 1.0);
+
+    // on a long text, every line fits and the first word of the next line wouldn't
+    let text = "11 2 111 22 1 ".repeat(20);
+    let spans = [
+        TextSpan::new(&text[..55], &font, small, &fill),
+        TextSpan::new(&text[55..], &font, large, &fill),
+    ];
+    let lines = rich_text_wrap(&spans, 200, &options);
+    let width =
+        |line: Vec<TextSpan>| RichLayout::new(&[line], 1.0, TextAlign::Left, None, &options).width;
+
+    let mut start = 0;
+    for (line, next) in lines.iter().zip(&lines[1..]) {
+        let line_text = line.iter().map(|span| span.text).collect::<String>();
+        start += text[start..].find(&line_text).unwrap();
+        let word = next[0].text.split(' ').next().unwrap();
+
+        assert!(width(line.clone()) <= 200.0);
+        let longer = start + line_text.len() + 1 + word.len();
+        assert!(width(slice(&spans, &span_ends(&spans), start, longer)) > 200.0);
+        start += line_text.len();
+    }
+    assert!(lines.len() > 10);
+
+    // line breaks are kept
+    let spans = [
+        TextSpan::new("11\n", &font, small, &fill),
+        TextSpan::new("\n22", &font, large, &fill),
+    ];
+    let lines = rich_text_wrap(&spans, i32::MAX, &options);
+    assert_eq!(lines.len(), 3);
+    assert_eq!((lines[1][0].text, lines[1][0].scale), ("", large));
+    assert_eq!(lines[2][0].text, "22");
+
+    // spans are placed in visual order: the two hebrew spans of a left to right line are
+    // reversed, a right to left line starts from its last span
+    let visual = |spans: &[TextSpan]| {
+        let layout = RichLayout::new(&[spans.to_vec()], 1.0, TextAlign::Left, None, &options);
+        let pieces = layout.pieces().collect::<Vec<_>>();
+        assert!(pieces.windows(2).all(|p| p[0].x < p[1].x));
+        pieces
+            .iter()
+            .map(|p| p.span.text.to_string())
+            .collect::<Vec<_>>()
+    };
+
+    let spans = [
+        TextSpan::new("ab ", &font, small, &fill),
+        TextSpan::new("אב", &font, large, &fill),
+        TextSpan::new(" גד", &font, small, &fill),
+    ];
+    assert_eq!(visual(&spans), vec!["ab ", " גד", "אב"]);
+
+    let spans = [
+        TextSpan::new("אב", &font, large, &fill),
+        TextSpan::new(": 11 גד", &font, small, &fill),
+    ];
+    let second = spans[1].text;
+    assert_eq!(visual(&spans), vec![second, second, second, "אב"]);
+
+    // a span with both directions is cut where the direction changes
+    let layout = RichLayout::new(&[spans.to_vec()], 1.0, TextAlign::Left, None, &options);
+    let texts = layout
+        .pieces()
+        .map(|p| p.layout.lines[0].text.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(texts, vec![" גד", "11", ": ", "אב"]);
+}
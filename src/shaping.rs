@@ -0,0 +1,749 @@
+use std::sync::OnceLock;
+
+use owned_ttf_parser::AsFaceRef;
+use unicode_bidi::{Level, ParagraphBidiInfo};
+use unicode_script::{Script, UnicodeScript};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_vo::Orientation;
+
+use crate::superfont::SuperFont;
+use crate::tabs::{TabAlign, TabStops};
+
+// font index used for the emoji placeholder font
+#[cfg(feature = "emoji")]
+pub(crate) const EMOJI_FONT_INDEX: usize = usize::MAX;
+
+/// A glyph produced by the shaping pass, with its advance and offsets in pixels.
+#[derive(Clone)]
+pub(crate) struct ShapedGlyph<'a, 'font> {
+    pub font: &'a rusttype::Font<'font>,
+    pub font_index: usize,
+    pub id: rusttype::GlyphId,
+    pub scale: rusttype::Scale,
+
+    /// byte offset of the cluster this glyph belongs to
+    pub cluster: usize,
+
+    pub x_advance: f32,
+    /// downwards, only set in vertical layout
+    pub y_advance: f32,
+    pub x_offset: f32,
+    pub y_offset: f32,
+
+    /// the glyph was shaped horizontally and is rotated 90° clockwise in vertical layout
+    pub sideways: bool,
+    /// the glyph belongs to a right-to-left run
+    pub rtl: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Run {
+    start: usize,
+    end: usize,
+    font_index: usize,
+    level: Level,
+    orientation: RunOrientation,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RunOrientation {
+    Horizontal,
+    /// vertical layout, shaped top-to-bottom
+    Upright,
+    /// vertical layout, shaped horizontally and rotated
+    Sideways,
+}
+
+#[inline]
+pub(crate) fn face<'a>(font: &'a rusttype::Font<'_>) -> &'a owned_ttf_parser::Face<'a> {
+    match font {
+        rusttype::Font::Ref(face) => face,
+        rusttype::Font::Owned(face) => face.as_face_ref(),
+    }
+}
+
+impl<'font> SuperFont<'font> {
+    #[inline]
+    pub(crate) fn font_at(&self, index: usize) -> &rusttype::Font<'font> {
+        #[cfg(feature = "emoji")]
+        if index == EMOJI_FONT_INDEX {
+            return &crate::emoji::EMOJI_FONT;
+        }
+
+        match index {
+            0 => &self.main,
+            i => &self.fallbacks[i - 1],
+        }
+    }
+
+    /// Picks the font for a grapheme cluster: the first font (main, then fallbacks) that
+    /// has glyphs for the whole cluster, otherwise the first font that has the base
+    /// character so that combining marks stay with their base.
+    pub(crate) fn font_index_for(&self, cluster: &str) -> usize {
+        let covers = |font: &rusttype::Font| {
+            cluster
+                .chars()
+                .all(|c| is_default_ignorable(c) || font.glyph(c).id().0 != 0)
+        };
+
+        if let Some(i) = self.fonts().position(covers) {
+            return i;
+        }
+
+        let Some(base) = cluster.chars().next() else {
+            return 0;
+        };
+
+        self.fonts()
+            .position(|font| font.glyph(base).id().0 != 0)
+            .unwrap_or(0)
+    }
+
+    #[inline]
+    fn fonts(&self) -> impl Iterator<Item = &rusttype::Font<'font>> {
+        std::iter::once(&self.main).chain(self.fallbacks.iter())
+    }
+}
+
+// characters that fonts are not expected to have glyphs for
+#[inline]
+fn is_default_ignorable(c: char) -> bool {
+    matches!(c, '\u{200b}'..='\u{200f}' | '\u{2060}'..='\u{2064}' | '\u{fe00}'..='\u{fe0f}')
+        || matches!(c, '\u{e0100}'..='\u{e01ef}')
+}
+
+/// Returns `Some(true)` if the first strong character of `text` is right-to-left,
+/// `Some(false)` if it is left-to-right and `None` if there is no strong character.
+pub(crate) fn base_direction_rtl(text: &str) -> Option<bool> {
+    match unicode_bidi::get_base_direction(text) {
+        unicode_bidi::Direction::Ltr => Some(false),
+        unicode_bidi::Direction::Rtl => Some(true),
+        unicode_bidi::Direction::Mixed => None,
+    }
+}
+
+// splits the text into runs of grapheme clusters that share a font, a script and a bidi
+// embedding level, `base` is the level of the paragraph (from the text when `None`)
+fn itemize(
+    font: &SuperFont,
+    text: &str,
+    base: Option<Level>,
+    emojis: bool,
+    vertical: bool,
+) -> Vec<Run> {
+    let bidi = ParagraphBidiInfo::new(text, base);
+    let levels = if bidi.has_rtl() {
+        bidi.reordered_levels(0..text.len())
+    } else {
+        Vec::new()
+    };
+
+    let mut runs: Vec<Run> = Vec::new();
+    let mut run_script = Script::Common;
+
+    for (i, cluster) in text.grapheme_indices(true) {
+        #[cfg(feature = "emoji")]
+        let font_index = if emojis && cluster.starts_with(crate::emoji::parse::PLACEHOLDER_EMOJI) {
+            EMOJI_FONT_INDEX
+        } else {
+            font.font_index_for(cluster)
+        };
+        #[cfg(not(feature = "emoji"))]
+        let font_index = {
+            let _ = emojis;
+            font.font_index_for(cluster)
+        };
+
+        let level = levels.get(i).copied().unwrap_or_else(Level::ltr);
+        let script = cluster
+            .chars()
+            .map(|c| c.script())
+            .find(|script| !matches!(script, Script::Common | Script::Inherited))
+            .unwrap_or(Script::Common);
+        let is_common = script == Script::Common;
+        let end = i + cluster.len();
+        let orientation = match cluster.chars().next().map(unicode_vo::char_orientation) {
+            _ if !vertical => RunOrientation::Horizontal,
+            Some(Orientation::Upright | Orientation::TransformedOrUpright) => {
+                RunOrientation::Upright
+            }
+            _ => RunOrientation::Sideways,
+        };
+
+        if let Some(run) = runs.last_mut() {
+            if run.font_index == font_index
+                && run.level == level
+                && run.orientation == orientation
+                && (is_common || run_script == Script::Common || script == run_script)
+            {
+                run.end = end;
+                if !is_common {
+                    run_script = script;
+                }
+                continue;
+            }
+        }
+
+        run_script = script;
+        runs.push(Run {
+            start: i,
+            end,
+            font_index,
+            level,
+            orientation,
+        });
+    }
+
+    // rule L2, reverse the order of runs from the highest level down
+    if !levels.is_empty() {
+        let run_levels = runs.iter().map(|run| run.level).collect::<Vec<_>>();
+        runs = ParagraphBidiInfo::reorder_visual(&run_levels)
+            .into_iter()
+            .map(|i| runs[i])
+            .collect();
+    }
+
+    runs
+}
+
+/// The rustybuzz faces of the fonts of a [`SuperFont`], each built the first time it
+/// shapes text and kept with the font it borrows its tables from.
+pub(crate) struct ShapingFaces<'f> {
+    // declared before `fonts`, so that they are dropped before the data they borrow
+    faces: Vec<OnceLock<Option<rustybuzz::Face<'static>>>>,
+    fonts: Vec<rusttype::Font<'f>>,
+}
+
+impl<'f> ShapingFaces<'f> {
+    pub(crate) fn new(main: &rusttype::Font<'f>, fallbacks: &[rusttype::Font<'f>]) -> Self {
+        let fonts = std::iter::once(main)
+            .chain(fallbacks)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        Self {
+            faces: fonts.iter().map(|_| OnceLock::new()).collect(),
+            fonts,
+        }
+    }
+
+    // the face of the font at `index`, as in `SuperFont::font_at`
+    fn get(&self, index: usize) -> Option<&rustybuzz::Face<'_>> {
+        #[cfg(feature = "emoji")]
+        if index == EMOJI_FONT_INDEX {
+            return EMOJI_FACE.as_ref();
+        }
+
+        let font = self.fonts.get(index)?;
+        self.faces[index]
+            .get_or_init(|| {
+                let face = rustybuzz::Face::from_face(face(font).clone())?;
+                // SAFETY: the face borrows the font data of `font`, which sits behind an
+                // `Arc` that `self.fonts` keeps alive and never changes, and it's only lent
+                // out for as long as `self` is borrowed
+                Some(unsafe {
+                    std::mem::transmute::<rustybuzz::Face<'_>, rustybuzz::Face<'static>>(face)
+                })
+            })
+            .as_ref()
+    }
+}
+
+#[cfg(feature = "emoji")]
+static EMOJI_FACE: once_cell::sync::Lazy<Option<rustybuzz::Face<'static>>> =
+    once_cell::sync::Lazy::new(|| {
+        rustybuzz::Face::from_face(face(&crate::emoji::EMOJI_FONT).clone())
+    });
+
+fn shape_str(
+    face: &rustybuzz::Face,
+    text: &str,
+    direction: rustybuzz::Direction,
+    features: &[rustybuzz::Feature],
+) -> rustybuzz::GlyphBuffer {
+    let mut buffer = rustybuzz::UnicodeBuffer::new();
+    buffer.push_str(text);
+    buffer.guess_segment_properties();
+    buffer.set_direction(direction);
+
+    rustybuzz::shape(face, features, buffer)
+}
+
+#[inline]
+fn horizontal(rtl: bool) -> rustybuzz::Direction {
+    if rtl {
+        rustybuzz::Direction::RightToLeft
+    } else {
+        rustybuzz::Direction::LeftToRight
+    }
+}
+
+// Kerning (in font units) between the clusters at the edges of two visually adjacent runs.
+// Runs of the same font and direction are only split where the script changes, shaping
+// the two clusters together picks up any kern / GPOS pair adjustment between them.
+fn boundary_kerning(
+    face: &rustybuzz::Face,
+    features: &[rustybuzz::Feature],
+    left: &str,
+    right: &str,
+    rtl: bool,
+) -> i32 {
+    let advance = |text: &str| -> i32 {
+        shape_str(face, text, horizontal(rtl), features)
+            .glyph_positions()
+            .iter()
+            .map(|pos| pos.x_advance)
+            .sum()
+    };
+
+    let pair = if rtl {
+        format!("{}{}", right, left)
+    } else {
+        format!("{}{}", left, right)
+    };
+
+    advance(&pair) - advance(left) - advance(right)
+}
+
+fn shape_run<'a, 'font>(
+    font: &'a SuperFont<'font>,
+    face: &rustybuzz::Face,
+    text: &str,
+    run: Run,
+    scale: rusttype::Scale,
+    glyphs: &mut Vec<ShapedGlyph<'a, 'font>>,
+) {
+    let rt_font = font.font_at(run.font_index);
+    let direction = match run.orientation {
+        RunOrientation::Upright => rustybuzz::Direction::TopToBottom,
+        _ => horizontal(run.level.is_rtl()),
+    };
+    let output = shape_str(
+        face,
+        &text[run.start..run.end],
+        direction,
+        font.features.as_slice(),
+    );
+
+    // rustybuzz works in font units, rusttype scales by the pixel height
+    let sx = rt_font.scale_for_pixel_height(scale.x);
+    let sy = rt_font.scale_for_pixel_height(scale.y);
+
+    let ttf = self::face(rt_font);
+    let has_vmtx = ttf.tables().vmtx.is_some();
+
+    for (info, pos) in output
+        .glyph_infos()
+        .iter()
+        .zip(output.glyph_positions().iter())
+    {
+        let id = rusttype::GlyphId(info.glyph_id as u16);
+        let (mut y_advance, mut y_offset) = (pos.y_advance, pos.y_offset);
+
+        // without vmtx every glyph gets an em box from the ascender to the descender,
+        // rustybuzz would otherwise align the glyph tops to the pen position
+        if run.orientation == RunOrientation::Upright && !has_vmtx {
+            let y_max = ttf
+                .glyph_bounding_box(owned_ttf_parser::GlyphId(id.0))
+                .map_or(0, |bb| bb.y_max as i32);
+            let height = ttf.ascender() as i32 - ttf.descender() as i32;
+
+            y_offset += y_max - ttf.ascender() as i32;
+            y_advance = -height;
+        }
+
+        let (x_advance, y_advance) = match run.orientation {
+            RunOrientation::Horizontal => (pos.x_advance as f32 * sx, 0.0),
+            RunOrientation::Upright => (0.0, -y_advance as f32 * sy),
+            RunOrientation::Sideways => (0.0, pos.x_advance as f32 * sx),
+        };
+
+        glyphs.push(ShapedGlyph {
+            font: rt_font,
+            font_index: run.font_index,
+            id,
+            scale,
+            cluster: run.start + info.cluster as usize,
+            x_advance,
+            y_advance,
+            x_offset: pos.x_offset as f32 * sx,
+            y_offset: y_offset as f32 * sy,
+            sideways: run.orientation == RunOrientation::Sideways,
+            rtl: run.level.is_rtl(),
+        });
+    }
+}
+
+/// Shapes `text` with the fonts of `font`, applying GSUB substitutions (ligatures,
+/// contextual forms, reordering) and GPOS / kern positioning per run.
+///
+/// The text is treated as a single line and reordered with the Unicode Bidirectional
+/// Algorithm, so glyphs come out in visual (left-to-right) order. `base` is the embedding
+/// level of the paragraph the line belongs to, it's found from the first strong character
+/// of the line when `None`. Glyphs from different fonts are never kerned against each
+/// other. Tabs move the text after them to `tab_stops`.
+pub(crate) fn shape<'a, 'font>(
+    font: &'a SuperFont<'font>,
+    text: &str,
+    scale: rusttype::Scale,
+    emoji_scale: Option<rusttype::Scale>,
+    base: Option<Level>,
+    tab_stops: &TabStops,
+) -> Vec<ShapedGlyph<'a, 'font>> {
+    let mut glyphs = shape_with(font, text, scale, emoji_scale, base, false);
+    apply_tabs(font, tab_stops, text, scale, &mut glyphs);
+    glyphs
+}
+
+/// Shapes `text` for vertical (top-to-bottom) layout. Characters that are upright in
+/// vertical text (CJK, kana, hangul...) are shaped top-to-bottom using the vertical
+/// metrics of the font, everything else is shaped horizontally and marked as sideways.
+/// [`TabStops`] don't apply here, tabs are shaped as sideways glyphs.
+pub(crate) fn shape_vertical<'a, 'font>(
+    font: &'a SuperFont<'font>,
+    text: &str,
+    scale: rusttype::Scale,
+) -> Vec<ShapedGlyph<'a, 'font>> {
+    shape_with(font, text, scale, None, None, true)
+}
+
+fn shape_with<'a, 'font>(
+    font: &'a SuperFont<'font>,
+    text: &str,
+    scale: rusttype::Scale,
+    emoji_scale: Option<rusttype::Scale>,
+    base: Option<Level>,
+    vertical: bool,
+) -> Vec<ShapedGlyph<'a, 'font>> {
+    let mut glyphs: Vec<ShapedGlyph> = Vec::with_capacity(text.len());
+    let mut prev: Option<Run> = None;
+
+    for run in itemize(font, text, base, emoji_scale.is_some(), vertical) {
+        #[cfg(feature = "emoji")]
+        let scale = match emoji_scale {
+            Some(emoji_scale) if run.font_index == EMOJI_FONT_INDEX => emoji_scale,
+            _ => scale,
+        };
+
+        let Some(face) = font.faces.get(run.font_index) else {
+            continue;
+        };
+
+        if let Some(prev) = prev.filter(|prev| {
+            prev.font_index == run.font_index
+                && prev.level == run.level
+                && prev.orientation == run.orientation
+                && run.orientation != RunOrientation::Upright
+                && !glyphs.is_empty()
+        }) {
+            let rtl = run.level.is_rtl();
+            let (left, right) = (&text[prev.start..prev.end], &text[run.start..run.end]);
+
+            // the clusters that touch visually
+            let (left, right) = if rtl {
+                (
+                    left.graphemes(true).next(),
+                    right.graphemes(true).next_back(),
+                )
+            } else {
+                (
+                    left.graphemes(true).next_back(),
+                    right.graphemes(true).next(),
+                )
+            };
+
+            if let (Some(left), Some(right)) = (left, right) {
+                let kerning = boundary_kerning(face, font.features.as_slice(), left, right, rtl);
+                if kerning != 0 {
+                    let sx = font.font_at(run.font_index).scale_for_pixel_height(scale.x);
+                    if let Some(last) = glyphs.last_mut() {
+                        match last.sideways {
+                            true => last.y_advance += kerning as f32 * sx,
+                            false => last.x_advance += kerning as f32 * sx,
+                        }
+                    }
+                }
+            }
+        }
+
+        shape_run(font, face, text, run, scale, &mut glyphs);
+        prev = Some(run);
+    }
+
+    apply_spacing(font, text, scale, vertical, &mut glyphs);
+    glyphs
+}
+
+#[inline]
+fn is_word_separator(c: char) -> bool {
+    matches!(c, ' ' | '\u{a0}' | '\u{1361}' | '\u{3000}')
+}
+
+// letter spacing goes after the last glyph of every cluster, so ligatures and marks
+// are not pulled apart, word spacing additionally after word separators
+fn apply_spacing(
+    font: &SuperFont,
+    text: &str,
+    scale: rusttype::Scale,
+    vertical: bool,
+    glyphs: &mut [ShapedGlyph],
+) {
+    let letter_spacing = font.letter_spacing.to_px(scale.x);
+    let word_spacing = font.word_spacing.to_px(scale.x);
+
+    if letter_spacing == 0.0 && word_spacing == 0.0 {
+        return;
+    }
+
+    for i in 0..glyphs.len() {
+        let cluster = glyphs[i].cluster;
+        if glyphs
+            .get(i + 1)
+            .is_some_and(|next| next.cluster == cluster)
+        {
+            continue;
+        }
+
+        let mut spacing = letter_spacing;
+        if text[cluster..].starts_with(is_word_separator) {
+            spacing += word_spacing;
+        }
+
+        let g = &mut glyphs[i];
+        if vertical {
+            g.y_advance += spacing;
+        } else {
+            g.x_advance += spacing;
+        }
+    }
+}
+
+// tabs get the advance that moves the text after them to their tab stop, and are drawn
+// as a space
+fn apply_tabs(
+    font: &SuperFont,
+    tab_stops: &TabStops,
+    text: &str,
+    scale: rusttype::Scale,
+    glyphs: &mut [ShapedGlyph],
+) {
+    if !text.contains('\t') {
+        return;
+    }
+
+    let is_tab = |g: &ShapedGlyph| text[g.cluster..].starts_with('\t');
+    let space = font.main.glyph(' ').scaled(scale).h_metrics().advance_width;
+    let mut caret = 0.0;
+
+    for i in 0..glyphs.len() {
+        if is_tab(&glyphs[i]) {
+            let (stop, align) = tab_stops.after(caret, scale.x, space);
+
+            // the text aligned at the stop, up to the next tab
+            let segment = glyphs[i + 1..].iter().take_while(|g| !is_tab(g));
+            let offset: f32 = match align {
+                TabAlign::Left => 0.0,
+                TabAlign::Right => segment.map(|g| g.x_advance).sum(),
+                TabAlign::Center => segment.map(|g| g.x_advance).sum::<f32>() / 2.0,
+                TabAlign::Decimal(separator) => segment
+                    .take_while(|g| !text[g.cluster..].starts_with(separator))
+                    .map(|g| g.x_advance)
+                    .sum(),
+            };
+
+            let g = &mut glyphs[i];
+            g.id = g.font.glyph(' ').id();
+            g.x_advance = (stop - offset - caret).max(0.0);
+            g.x_offset = 0.0;
+        }
+
+        caret += glyphs[i].x_advance;
+    }
+}
+
+#[cfg(feature = "emoji")]
+#[test]
+fn itemize_test() {
+    let font = SuperFont::new(crate::emoji::EMOJI_FONT.clone(), vec![]);
+
+    let text = "ab مرحبا cd";
+    let runs = itemize(&font, text, None, false, false)
+        .iter()
+        .map(|run| &text[run.start..run.end])
+        .collect::<Vec<_>>();
+
+    assert_eq!(runs, vec!["ab ", "مرحبا", " cd"]);
+
+    // right-to-left paragraph, the embedded english run stays in place
+    let text = "שלום abc עולם";
+    let runs = itemize(&font, text, None, false, false)
+        .iter()
+        .map(|run| &text[run.start..run.end])
+        .collect::<Vec<_>>();
+
+    assert_eq!(runs, vec![" עולם", "abc", "שלום "]);
+
+    let text = "hi 😀 there";
+    let runs = itemize(&font, text, None, true, false)
+        .iter()
+        .map(|run| (&text[run.start..run.end], run.font_index))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        runs,
+        vec![("hi ", 0), ("😀", EMOJI_FONT_INDEX), (" there", 0)]
+    );
+}
+
+#[cfg(feature = "emoji")]
+#[test]
+fn font_fallback_test() {
+    // the emoji font has digits and the keycap mark, but no letters or acute accent
+    let font = SuperFont::new(
+        crate::emoji::EMOJI_FONT.clone(),
+        vec![crate::superfont::test_font()],
+    );
+
+    assert_eq!(font.font_index_for("1"), 0);
+    assert_eq!(font.font_index_for("1\u{20e3}"), 0);
+    assert_eq!(font.font_index_for("e"), 1);
+
+    // the fallback covers the whole cluster, so the base goes with its mark
+    assert_eq!(font.font_index_for("1\u{301}"), 1);
+    // no font covers the whole cluster, the mark stays with the font of its base
+    assert_eq!(font.font_index_for("e\u{20e3}"), 1);
+
+    let text = "11\u{301}1";
+    let runs = itemize(&font, text, None, false, false)
+        .iter()
+        .map(|run| (&text[run.start..run.end], run.font_index))
+        .collect::<Vec<_>>();
+    assert_eq!(runs, vec![("1", 0), ("1\u{301}", 1), ("1", 0)]);
+
+    let glyphs = shape(
+        &font,
+        text,
+        rusttype::Scale::uniform(20.0),
+        None,
+        None,
+        &TabStops::default(),
+    );
+    assert_eq!(
+        glyphs
+            .iter()
+            .map(|g| (g.cluster, std::ptr::eq(g.font, font.font_at(1))))
+            .collect::<Vec<_>>(),
+        vec![(0, false), (1, true), (1, true), (4, false)]
+    );
+}
+
+#[test]
+fn kerning_test() {
+    use crate::features::FontFeatures;
+    use crate::superfont::{static_test_font, test_font};
+
+    let scale = rusttype::Scale::uniform(20.0);
+    let advance = |font: &SuperFont, text: &str| -> f32 {
+        shape(font, text, scale, None, None, &TabStops::default())
+            .iter()
+            .map(|g| g.x_advance)
+            .sum()
+    };
+
+    // GPOS pair kerning pulls the "o" under the "T"
+    let font = SuperFont::new(static_test_font(), vec![]);
+    let kerned = advance(&font, "To");
+    assert!(kerned < advance(&font, "T") + advance(&font, "o") - 1.0);
+
+    let face = font.faces.get(0).unwrap();
+    let kerning = boundary_kerning(face, &[], "T", "o", false);
+    let sx = font.main.scale_for_pixel_height(scale.x);
+    assert!(kerning < 0);
+    assert!(
+        (advance(&font, "T") + advance(&font, "o") + kerning as f32 * sx - kerned).abs() < 1e-3
+    );
+    // right-to-left, "T" is the logical first of the visual pair "oT"
+    assert_eq!(boundary_kerning(face, &[], "o", "T", true), kerning);
+
+    let features = FontFeatures::new().disable("kern");
+    assert_eq!(
+        boundary_kerning(face, features.as_slice(), "T", "o", false),
+        0
+    );
+
+    // "Ṫ" only comes from the fallback, so it isn't kerned against the "o" of the main font
+    let font = SuperFont::new(test_font(), vec![static_test_font()]);
+    let glyphs = shape(&font, "Ṫo", scale, None, None, &TabStops::default());
+    assert!(std::ptr::eq(glyphs[0].font, font.font_at(1)));
+    assert!(std::ptr::eq(glyphs[1].font, font.font_at(0)));
+
+    let single = SuperFont::new(static_test_font(), vec![]);
+    assert!(advance(&single, "Ṫo") < advance(&single, "Ṫ") + advance(&single, "o") - 1.0);
+    assert_eq!(glyphs[0].x_advance, advance(&single, "Ṫ"));
+    assert_eq!(
+        advance(&font, "Ṫo"),
+        advance(&single, "Ṫ") + advance(&font, "o")
+    );
+}
+
+#[cfg(feature = "emoji")]
+#[test]
+fn vertical_advance_test() {
+    use crate::superfont::static_test_font;
+
+    let scale = rusttype::Scale::uniform(20.0);
+    let advances = |font: &SuperFont, text: &str| {
+        shape_vertical(font, text, scale)
+            .iter()
+            .map(|g| (g.sideways, g.y_advance))
+            .collect::<Vec<_>>()
+    };
+
+    // the emoji font has vmtx, upright glyphs advance by their vertical metrics
+    let font = SuperFont::new(crate::emoji::EMOJI_FONT.clone(), vec![]);
+    let ttf = face(&font.main);
+    let id = ttf.glyph_index('😀').unwrap();
+    let advance =
+        ttf.glyph_ver_advance(id).unwrap() as f32 * font.main.scale_for_pixel_height(scale.y);
+    assert!(ttf.tables().vmtx.is_some());
+    assert_eq!(advances(&font, "😀😀"), vec![(false, advance); 2]);
+
+    // without vmtx they advance by an em box from the ascender to the descender
+    let font = SuperFont::new(static_test_font(), vec![]);
+    assert!(face(&font.main).tables().vmtx.is_none());
+    let glyphs = advances(&font, "©§1");
+    assert_eq!(&glyphs[..2], &[(false, scale.y); 2]);
+    assert!(glyphs[2].0);
+}
+
+#[test]
+fn shaping_faces_test() {
+    use crate::superfont::test_font;
+    use crate::variations::FontVariations;
+
+    let scale = rusttype::Scale::uniform(20.0);
+    let advance = |font: &SuperFont| -> f32 {
+        shape(font, "abc", scale, None, None, &TabStops::default())
+            .iter()
+            .map(|g| g.x_advance)
+            .sum()
+    };
+
+    // the face is built once and shared by the clones of the font
+    let font = SuperFont::new(test_font(), vec![]);
+    let face = font.faces.get(0).unwrap();
+    assert!(std::ptr::eq(face, font.clone().faces.get(0).unwrap()));
+    assert!(font.faces.get(1).is_none());
+
+    // fonts that own their data shape the same, their faces borrow it
+    let data = include_bytes!("../tests/fonts/Cantarell-VF.otf").to_vec();
+    let owned = SuperFont::new(rusttype::Font::try_from_vec(data).unwrap(), vec![]);
+    assert_eq!(advance(&owned.clone()), advance(&font));
+
+    // variations get faces of their own
+    let bold = font
+        .clone()
+        .with_variations(&FontVariations::new().weight(700.0));
+    assert!(!std::ptr::eq(face, bold.faces.get(0).unwrap()));
+    assert!(advance(&bold) > advance(&font));
+}
@@ -1,19 +1,73 @@
 use std::sync::Arc;
 
+use crate::features::FontFeatures;
+use crate::shaping::ShapingFaces;
+use crate::variations::FontVariations;
+
+/// A spacing length, either in pixels or relative to the font size (`scale.x`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Spacing {
+    Px(f32),
+    Em(f32),
+}
+
+impl Default for Spacing {
+    fn default() -> Self {
+        Spacing::Px(0.0)
+    }
+}
+
+impl Spacing {
+    pub fn to_px(&self, size: f32) -> f32 {
+        match self {
+            Spacing::Px(px) => *px,
+            Spacing::Em(em) => em * size,
+        }
+    }
+}
+
+/// Bold and italic styles faked from the outlines of the fonts, for fonts that don't
+/// have them: bold glyphs are drawn a few times side by side, italic glyphs are slanted.
+/// Glyph advances and measured sizes don't change.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SyntheticStyle {
+    pub bold: bool,
+    pub italic: bool,
+}
+
 #[derive(Clone)]
 pub struct SuperFont<'f> {
     pub(crate) main: rusttype::Font<'f>,
     pub(crate) fallbacks: Arc<Vec<rusttype::Font<'f>>>,
 
+    /// extra space added after every grapheme cluster (tracking)
+    pub letter_spacing: Spacing,
+    /// extra space added to every word separator (on top of the letter spacing)
+    pub word_spacing: Spacing,
+
+    /// OpenType features used when shaping with any of the fonts
+    pub features: FontFeatures,
+
+    /// bold and italic faked for fonts without those styles
+    pub synthetic: SyntheticStyle,
+
     #[cfg(feature = "emoji")]
     pub emoji_options: crate::emoji::EmojiOptions,
+
+    // the rustybuzz faces of `main` and `fallbacks`, shared by the clones of the font
+    pub(crate) faces: Arc<ShapingFaces<'f>>,
 }
 
 impl<'f> SuperFont<'f> {
     pub fn new(font: rusttype::Font<'f>, fallbacks: Vec<rusttype::Font<'f>>) -> SuperFont<'f> {
         Self {
+            faces: Arc::new(ShapingFaces::new(&font, &fallbacks)),
             main: font,
             fallbacks: Arc::new(fallbacks),
+            letter_spacing: Spacing::default(),
+            word_spacing: Spacing::default(),
+            features: FontFeatures::default(),
+            synthetic: SyntheticStyle::default(),
             #[cfg(feature = "emoji")]
             emoji_options: crate::emoji::EmojiOptions::default(),
         }
@@ -27,6 +81,46 @@ impl<'f> SuperFont<'f> {
         &self.fallbacks
     }
 
+    pub fn with_letter_spacing(mut self, spacing: Spacing) -> Self {
+        self.letter_spacing = spacing;
+        self
+    }
+
+    pub fn with_word_spacing(mut self, spacing: Spacing) -> Self {
+        self.word_spacing = spacing;
+        self
+    }
+
+    /// Sets the OpenType features to shape with. Cloning a `SuperFont` is cheap, so
+    /// `font.clone().with_features(..)` can be used to pick features for a single draw.
+    pub fn with_features(mut self, features: FontFeatures) -> Self {
+        self.features = features;
+        self
+    }
+
+    pub fn with_synthetic(mut self, synthetic: SyntheticStyle) -> Self {
+        self.synthetic = synthetic;
+        self
+    }
+
+    /// Sets variable font axis values on the main font and on every fallback that has
+    /// those axes, e.g. `font.with_variations(&FontVariations::new().weight(700.0))`.
+    pub fn with_variations(mut self, variations: &FontVariations) -> Self {
+        if variations.is_empty() {
+            return self;
+        }
+
+        self.main = variations.apply(&self.main);
+        self.fallbacks = Arc::new(
+            self.fallbacks
+                .iter()
+                .map(|font| variations.apply(font))
+                .collect(),
+        );
+        self.faces = Arc::new(ShapingFaces::new(&self.main, &self.fallbacks));
+        self
+    }
+
     #[cfg(feature = "emoji")]
     pub fn with_emoji_options(
         font: rusttype::Font<'f>,
@@ -34,9 +128,32 @@ impl<'f> SuperFont<'f> {
         emoji_options: crate::emoji::EmojiOptions,
     ) -> SuperFont<'f> {
         Self {
+            faces: Arc::new(ShapingFaces::new(&font, &fallbacks)),
             main: font,
             fallbacks: Arc::new(fallbacks),
+            letter_spacing: Spacing::default(),
+            word_spacing: Spacing::default(),
+            features: FontFeatures::default(),
+            synthetic: SyntheticStyle::default(),
             emoji_options,
         }
     }
 }
+
+// Cantarell, a variable font (wght) with combining marks, see tests/fonts/OFL.txt for its
+// license
+#[cfg(test)]
+pub(crate) fn test_font() -> rusttype::Font<'static> {
+    rusttype::Font::try_from_bytes(include_bytes!("../tests/fonts/Cantarell-VF.otf") as &[u8])
+        .expect("Failed to load test font")
+}
+
+// DejaVu Sans ExtraLight, a static font with GPOS pair kerning, see
+// tests/fonts/DejaVu-LICENSE.txt for its license
+#[cfg(test)]
+pub(crate) fn static_test_font() -> rusttype::Font<'static> {
+    rusttype::Font::try_from_bytes(
+        include_bytes!("../tests/fonts/DejaVuSans-ExtraLight.ttf") as &[u8]
+    )
+    .expect("Failed to load test font")
+}
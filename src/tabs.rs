@@ -0,0 +1,169 @@
+use crate::superfont::Spacing;
+
+// tab stops are this many spaces apart by default
+const DEFAULT_TAB_SIZE: f32 = 8.0;
+
+/// How the text after a tab, up to the next tab or the end of the line, is aligned at its
+/// tab stop.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TabAlign {
+    /// starts at the stop
+    #[default]
+    Left,
+    /// ends at the stop
+    Right,
+    /// is centered on the stop
+    Center,
+    /// has its first decimal separator (usually `'.'`) at the stop, text without one ends at
+    /// the stop
+    Decimal(char),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TabStop {
+    /// from the start of the line
+    pub position: Spacing,
+    pub align: TabAlign,
+}
+
+/// Where a tab (`\t`) moves the text after it: to the next explicit stop, and past the
+/// last one to the next multiple of the interval (left aligned). Without an interval stops
+/// are every 8 spaces of the main font.
+///
+/// Tabs are collapsed like spaces when wrapping with `WhiteSpace::Normal` or
+/// `WhiteSpace::PreLine`, `Pre` and `PreWrap` keep them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TabStops {
+    interval: Option<Spacing>,
+    stops: Vec<TabStop>,
+}
+
+impl TabStops {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Left aligned stops every `interval`, e.g. `TabStops::every(Spacing::Px(80.0))`.
+    pub fn every(interval: Spacing) -> Self {
+        Self::new().interval(interval)
+    }
+
+    pub fn interval(mut self, interval: Spacing) -> Self {
+        self.interval = Some(interval);
+        self
+    }
+
+    /// Adds a stop at `position`, e.g. `.stop(Spacing::Px(200.0), TabAlign::Decimal('.'))`
+    /// for a column of prices.
+    pub fn stop(mut self, position: Spacing, align: TabAlign) -> Self {
+        self.stops.push(TabStop { position, align });
+        self
+    }
+
+    // the position and alignment of the first stop after `caret`, `size` is the font size
+    // and `space` the width of a space
+    pub(crate) fn after(&self, caret: f32, size: f32, space: f32) -> (f32, TabAlign) {
+        let stop = self
+            .stops
+            .iter()
+            .map(|stop| (stop.position.to_px(size), stop.align))
+            .filter(|(position, _)| *position > caret)
+            .min_by(|a, b| a.0.total_cmp(&b.0));
+        if let Some(stop) = stop {
+            return stop;
+        }
+
+        let interval = self
+            .interval
+            .map_or(space * DEFAULT_TAB_SIZE, |interval| interval.to_px(size));
+        if interval <= 0.0 {
+            return (caret, TabAlign::Left);
+        }
+
+        (
+            ((caret / interval).floor() + 1.0) * interval,
+            TabAlign::Left,
+        )
+    }
+}
+
+#[cfg(feature = "emoji")]
+#[test]
+fn tab_stops_test() {
+    use crate::{
+        layout::{LayoutOptions, TextLayout},
+        outliner::TextAlign,
+        wrap::{WhiteSpace, WrapStyle},
+    };
+
+    let font = crate::superfont::SuperFont::new(crate::emoji::EMOJI_FONT.clone(), vec![]);
+    let scale = rusttype::Scale::uniform(20.0);
+
+    let positions = |tab_stops: &TabStops, text: &str| {
+        crate::shaping::shape(&font, text, scale, None, None, tab_stops)
+            .iter()
+            .scan(0.0, |caret, g| {
+                let x = *caret;
+                *caret += g.x_advance;
+                Some(x)
+            })
+            .collect::<Vec<f32>>()
+    };
+    let advance = |text: &str| -> f32 {
+        crate::shaping::shape(&font, text, scale, None, None, &TabStops::default())
+            .iter()
+            .map(|g| g.x_advance)
+            .sum()
+    };
+
+    // every 8 spaces by default
+    let space = font.main.glyph(' ').scaled(scale).h_metrics().advance_width;
+    let default = TabStops::default();
+    assert_eq!(positions(&default, "1\t1")[2], space * 8.0);
+    assert_eq!(positions(&default, "1\t1\t1")[4], space * 16.0);
+
+    let tab_stops = TabStops::every(Spacing::Px(50.0))
+        .stop(Spacing::Px(100.0), TabAlign::Right)
+        .stop(Spacing::Px(200.0), TabAlign::Decimal('#'));
+
+    let x = positions(&tab_stops, "11\t22\t3#45");
+    assert_eq!(x[3], 100.0 - advance("22"));
+    assert_eq!(x[7], 200.0);
+
+    // past the explicit stops, every 50 pixels
+    let x = positions(&tab_stops, "\t1\t1\t1");
+    assert_eq!(x[5], 250.0);
+
+    // tabs are drawn as spaces
+    let glyphs = crate::shaping::shape(&font, "1\t1", scale, None, None, &tab_stops);
+    assert_eq!(glyphs[1].id, font.main.glyph(' ').id());
+
+    // wrapping collapses tabs into spaces unless whitespace is preserved
+    let wrapped = |white_space| {
+        let options = LayoutOptions::new()
+            .with_tab_stops(tab_stops.clone())
+            .with_white_space(white_space);
+        let layout = TextLayout::wrapped(
+            "1\t1",
+            300.0,
+            &font,
+            scale,
+            1.0,
+            TextAlign::Left,
+            WrapStyle::Word,
+            &options,
+        );
+        let line = &layout.lines[0];
+        (line.text.clone(), line.glyphs[2].glyph.position().x)
+    };
+
+    assert_eq!(
+        wrapped(WhiteSpace::Normal),
+        ("1 1".to_string(), positions(&default, "1 1")[2])
+    );
+    assert_eq!(wrapped(WhiteSpace::PreLine).0, "1 1");
+    // right aligned at the first stop
+    let stop = 100.0 - advance("1");
+    assert_eq!(wrapped(WhiteSpace::PreWrap), ("1\t1".to_string(), stop));
+    assert_eq!(wrapped(WhiteSpace::Pre), ("1\t1".to_string(), stop));
+}
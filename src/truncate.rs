@@ -0,0 +1,398 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{
+    hit_test::source_offsets,
+    layout::LayoutOptions,
+    superfont::SuperFont,
+    wrap::{text_wrap_with_options, WhiteSpace, WrapStyle},
+};
+
+pub const ELLIPSIS: char = '…';
+
+/// Where the text is cut when it doesn't fit, and the ellipsis put.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EllipsisPosition {
+    /// `"a long sente…"`
+    #[default]
+    End,
+    /// `"…ong sentence"`
+    Start,
+    /// `"a long…tence.txt"`, keeps the extension of file names visible.
+    Middle,
+}
+
+/// Shortens `text` to fit in `width`, replacing the removed graphemes with an ellipsis
+/// measured in the same font. Text that already fits is returned as is.
+pub fn text_truncate(
+    text: &str,
+    width: i32,
+    font: &SuperFont,
+    scale: rusttype::Scale,
+    position: EllipsisPosition,
+    width_fn: impl Fn(rusttype::Scale, &SuperFont, &str) -> i32,
+) -> String {
+    match truncate(text, width, font, scale, position, false, &width_fn) {
+        Some((truncated, _)) => truncated,
+        None => text.to_string(),
+    }
+}
+
+/// Wraps `text` like [`text_wrap_with_options`], keeping at most `max_lines` lines. When lines are left
+/// out, the text they had is cut at `position`:
+/// - `End` keeps the first lines and ends the last one with an ellipsis
+/// - `Start` keeps the last lines and starts the first one with an ellipsis
+/// - `Middle` keeps the first lines, the last one shows the start and the end of the rest
+#[allow(clippy::too_many_arguments)]
+pub fn text_wrap_truncated(
+    text: &str,
+    width: i32,
+    max_lines: usize,
+    font: &SuperFont,
+    scale: rusttype::Scale,
+    wrap_style: WrapStyle,
+    position: EllipsisPosition,
+    options: &LayoutOptions,
+    width_fn: impl Fn(rusttype::Scale, &SuperFont, &str) -> i32,
+) -> Vec<String> {
+    wrap_truncated(
+        text, width, max_lines, font, scale, wrap_style, position, options, &width_fn,
+    )
+    .0
+}
+
+#[cfg(feature = "emoji")]
+/// Same as [`text_truncate`], measuring with emojis. Returns the emojis that are still in
+/// the truncated text.
+///
+/// assumes that emojis were parsed out
+pub fn parsed_text_truncate_with_emojis(
+    text: &str,
+    emojis: &[crate::emoji::source::EmojiType],
+    width: i32,
+    font: &SuperFont,
+    scale: rusttype::Scale,
+    position: EllipsisPosition,
+    options: &LayoutOptions,
+) -> (String, Vec<crate::emoji::source::EmojiType>) {
+    match truncate(
+        text,
+        width,
+        font,
+        scale,
+        position,
+        false,
+        &|scale, font, text| {
+            crate::layout::TextLayout::parsed_ink_width_with_emojis(text, font, scale, options)
+        },
+    ) {
+        Some((truncated, at)) => {
+            let emojis = kept_emojis(emojis, &truncated[..at], &truncated[at..]);
+            (truncated, emojis)
+        }
+        None => (text.to_string(), emojis.to_vec()),
+    }
+}
+
+#[cfg(feature = "emoji")]
+/// Same as [`text_wrap_truncated`], measuring with emojis. Returns the emojis that are
+/// still in the lines.
+///
+/// assumes that emojis were parsed out
+#[allow(clippy::too_many_arguments)]
+pub fn parsed_text_wrap_truncated_with_emojis(
+    text: &str,
+    emojis: &[crate::emoji::source::EmojiType],
+    width: i32,
+    max_lines: usize,
+    font: &SuperFont,
+    scale: rusttype::Scale,
+    wrap_style: WrapStyle,
+    position: EllipsisPosition,
+    options: &LayoutOptions,
+) -> (Vec<String>, Vec<crate::emoji::source::EmojiType>) {
+    let (lines, ellipsis) = wrap_truncated(
+        text,
+        width,
+        max_lines,
+        font,
+        scale,
+        wrap_style,
+        position,
+        options,
+        &|scale, font, text| {
+            crate::layout::TextLayout::parsed_ink_width_with_emojis(text, font, scale, options)
+        },
+    );
+
+    let emojis = match ellipsis {
+        Some((line, at)) => kept_emojis(
+            emojis,
+            &format!("{}{}", lines[..line].concat(), &lines[line][..at]),
+            &format!("{}{}", &lines[line][at..], lines[line + 1..].concat()),
+        ),
+        None => emojis.to_vec(),
+    };
+
+    (lines, emojis)
+}
+
+#[cfg(feature = "emoji")]
+// everything before the ellipsis comes from the start of the text and everything after it
+// from the end, so are their emojis
+fn kept_emojis(
+    emojis: &[crate::emoji::source::EmojiType],
+    before: &str,
+    after: &str,
+) -> Vec<crate::emoji::source::EmojiType> {
+    let count = |s: &str| s.matches(crate::emoji::parse::PLACEHOLDER_EMOJI).count();
+    let (head, tail) = (count(before), count(after));
+
+    emojis[..head.min(emojis.len())]
+        .iter()
+        .chain(&emojis[emojis.len().saturating_sub(tail)..])
+        .cloned()
+        .collect()
+}
+
+// the wrapped lines, and the line and byte offset of the ellipsis if lines were left out
+#[allow(clippy::too_many_arguments)]
+fn wrap_truncated(
+    text: &str,
+    width: i32,
+    max_lines: usize,
+    font: &SuperFont,
+    scale: rusttype::Scale,
+    wrap_style: WrapStyle,
+    position: EllipsisPosition,
+    options: &LayoutOptions,
+    width_fn: &dyn Fn(rusttype::Scale, &SuperFont, &str) -> i32,
+) -> (Vec<String>, Option<(usize, usize)>) {
+    let mut lines = text_wrap_with_options(text, width, font, scale, wrap_style, options, width_fn);
+
+    if lines.len() <= max_lines {
+        return (lines, None);
+    }
+    if max_lines == 0 {
+        return (Vec::new(), None);
+    }
+
+    // the text of the lines left out goes to the line that shows the ellipsis, which
+    // always gets one, even if that text happens to fit. It's sliced from `text` where the
+    // lines start or end, wrapping could have changed its whitespace.
+    let preserve = matches!(options.white_space, WhiteSpace::Pre | WhiteSpace::PreWrap);
+    let offsets = source_offsets(text, lines.iter().map(String::as_str), preserve);
+    let line = match position {
+        EllipsisPosition::Start => {
+            let first = lines.len() - max_lines;
+            let end = offsets[first][lines[first].len()];
+            lines.drain(..=first);
+            lines.insert(0, rest_line(&text[..end], position, options.white_space));
+            0
+        }
+        EllipsisPosition::End | EllipsisPosition::Middle => {
+            let start = offsets[max_lines - 1][0];
+            lines.truncate(max_lines - 1);
+            lines.push(rest_line(&text[start..], position, options.white_space));
+            max_lines - 1
+        }
+    };
+
+    match truncate(&lines[line], width, font, scale, position, true, width_fn) {
+        Some((truncated, at)) => {
+            lines[line] = truncated;
+            (lines, Some((line, at)))
+        }
+        None => (lines, None),
+    }
+}
+
+// the text left out of wrapped lines as a single line. A hard break ends the paragraph the
+// ellipsis is in, only `Middle` shows text on both sides of breaks.
+fn rest_line(text: &str, position: EllipsisPosition, white_space: WhiteSpace) -> String {
+    let text = match (white_space, position) {
+        (WhiteSpace::Normal, _) | (_, EllipsisPosition::Middle) => {
+            text.lines().collect::<Vec<_>>().join(" ")
+        }
+        (_, EllipsisPosition::End) => text.lines().next().unwrap_or_default().to_string(),
+        (_, EllipsisPosition::Start) => text.lines().last().unwrap_or_default().to_string(),
+    };
+
+    match white_space {
+        WhiteSpace::Normal | WhiteSpace::PreLine => {
+            text.split_whitespace().collect::<Vec<_>>().join(" ")
+        }
+        WhiteSpace::Pre | WhiteSpace::PreWrap => text,
+    }
+}
+
+// the text with the most graphemes that fits with an ellipsis, and the byte offset of the
+// ellipsis. `None` when the text fits and `force` isn't set.
+fn truncate(
+    text: &str,
+    width: i32,
+    font: &SuperFont,
+    scale: rusttype::Scale,
+    position: EllipsisPosition,
+    force: bool,
+    width_fn: &dyn Fn(rusttype::Scale, &SuperFont, &str) -> i32,
+) -> Option<(String, usize)> {
+    if !force && width_fn(scale, font, text) <= width {
+        return None;
+    }
+
+    let bounds = text
+        .grapheme_indices(true)
+        .map(|(i, _)| i)
+        .chain(std::iter::once(text.len()))
+        .collect::<Vec<_>>();
+    let graphemes = bounds.len() - 1;
+
+    // the text keeping `n` graphemes
+    let shorten = |n: usize| {
+        let (head, tail) = match position {
+            EllipsisPosition::End => (n, 0),
+            EllipsisPosition::Start => (0, n),
+            EllipsisPosition::Middle => (n - n / 2, n / 2),
+        };
+
+        let head = text[..bounds[head]].trim_end();
+        let tail = text[bounds[graphemes - tail]..].trim_start();
+        (format!("{head}{ELLIPSIS}{tail}"), head.len())
+    };
+
+    // binary search for the most graphemes that fit, an ellipsis alone is kept even if
+    // it doesn't fit
+    let (mut lo, mut hi) = (
+        0,
+        if force {
+            graphemes
+        } else {
+            graphemes.saturating_sub(1)
+        },
+    );
+    while lo < hi {
+        let mid = (lo + hi).div_ceil(2);
+
+        if width_fn(scale, font, &shorten(mid).0) <= width {
+            lo = mid;
+        } else {
+            hi = mid - 1;
+        }
+    }
+
+    Some(shorten(lo))
+}
+
+#[cfg(feature = "emoji")]
+#[test]
+fn text_truncate_test() {
+    let font = SuperFont::new(crate::emoji::EMOJI_FONT.clone(), vec![]);
+    let scale = rusttype::Scale::uniform(20.0);
+    let width = crate::measure::text_width(scale, &font, "1234…");
+
+    let truncate = |text, position| {
+        text_truncate(
+            text,
+            width,
+            &font,
+            scale,
+            position,
+            crate::measure::text_width,
+        )
+    };
+
+    assert_eq!(truncate("12", EllipsisPosition::End), "12");
+    assert_eq!(truncate("12345678", EllipsisPosition::End), "1234…");
+    assert_eq!(truncate("12345678", EllipsisPosition::Start), "…5678");
+    assert_eq!(truncate("12345678", EllipsisPosition::Middle), "12…78");
+
+    let width = crate::measure::text_width(scale, &font, "11 22");
+    let wrap = |max_lines, position| {
+        text_wrap_truncated(
+            "11 22 11 22 11 22",
+            width,
+            max_lines,
+            &font,
+            scale,
+            WrapStyle::Word,
+            position,
+            &LayoutOptions::default(),
+            crate::measure::text_width,
+        )
+    };
+
+    assert_eq!(
+        wrap(3, EllipsisPosition::End),
+        vec!["11 22", "11 22", "11 22"]
+    );
+    assert_eq!(wrap(2, EllipsisPosition::End), vec!["11 22", "11 2…"]);
+    assert_eq!(wrap(2, EllipsisPosition::Start), vec!["…1 22", "11 22"]);
+    assert_eq!(wrap(2, EllipsisPosition::Middle), vec!["11 22", "11…22"]);
+    assert!(wrap(0, EllipsisPosition::End).is_empty());
+
+    // the text left out is taken from the text, not from the wrapped lines: no spaces are
+    // added between characters and hard breaks end the line with the ellipsis
+    let chars = text_wrap_truncated(
+        "111122223333",
+        crate::measure::text_width(scale, &font, "1111"),
+        2,
+        &font,
+        scale,
+        WrapStyle::Character,
+        EllipsisPosition::End,
+        &LayoutOptions::default(),
+        crate::measure::text_width,
+    );
+    assert_eq!(chars[0], "1111");
+    assert!(chars[1].starts_with("22") && !chars[1].contains(' '));
+
+    let options = LayoutOptions::new().with_white_space(WhiteSpace::PreLine);
+    let wrap = |position| {
+        text_wrap_truncated(
+            "11\n22\n33",
+            crate::measure::text_width(scale, &font, "11 22 33"),
+            2,
+            &font,
+            scale,
+            WrapStyle::Word,
+            position,
+            &options,
+            crate::measure::text_width,
+        )
+    };
+    assert_eq!(wrap(EllipsisPosition::End), vec!["11", "22…"]);
+    assert_eq!(wrap(EllipsisPosition::Start), vec!["…22", "33"]);
+    assert_eq!(wrap(EllipsisPosition::Middle), vec!["11", "22…33"]);
+
+    let (text, emojis) = crate::emoji::parse::parse_out_emojis("1🙂2345😀", false, false);
+    let (truncated, kept) = parsed_text_truncate_with_emojis(
+        &text,
+        &emojis,
+        crate::measure::text_width(scale, &font, "12…"),
+        &font,
+        scale,
+        EllipsisPosition::Start,
+        &options,
+    );
+    assert!(truncated.starts_with(ELLIPSIS));
+    assert_eq!(kept.len(), 1);
+
+    // measured with the same tab stops as it's drawn with
+    let width = crate::measure::text_width(scale, &font, "1\t2");
+    let tabbed = options.clone().with_tab_stops(crate::tabs::TabStops::every(
+        crate::superfont::Spacing::Px(width as f32),
+    ));
+    let truncate = |options| {
+        parsed_text_truncate_with_emojis(
+            "1\t2",
+            &[],
+            width,
+            &font,
+            scale,
+            EllipsisPosition::End,
+            options,
+        )
+    };
+    assert_eq!(truncate(&options).0, "1\t2");
+    assert!(truncate(&tabbed).0.ends_with(ELLIPSIS));
+}
@@ -0,0 +1,217 @@
+use std::str::FromStr;
+
+use owned_ttf_parser::{AsFaceRef, FaceMut};
+use rusttype::Font;
+
+/// Variable font axis values (`wght`, `wdth`, `slnt`, `opsz`...), in the units of the axis
+/// (`wght=700`, `wdth=75`, `slnt=-10`).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FontVariations {
+    variations: Vec<rustybuzz::Variation>,
+}
+
+impl FontVariations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the value of an axis. Values outside of the range of the axis are clamped.
+    pub fn set(mut self, tag: &str, value: f32) -> Self {
+        let tag = rustybuzz::Tag::from_bytes_lossy(tag.as_bytes());
+
+        self.variations.retain(|v| v.tag != tag);
+        self.variations.push(rustybuzz::Variation { tag, value });
+        self
+    }
+
+    pub fn weight(self, value: f32) -> Self {
+        self.set("wght", value)
+    }
+
+    pub fn width(self, value: f32) -> Self {
+        self.set("wdth", value)
+    }
+
+    pub fn slant(self, value: f32) -> Self {
+        self.set("slnt", value)
+    }
+
+    /// Parses a comma or whitespace separated list of axis values, e.g. `"wght=700, wdth=75"`.
+    pub fn parse(s: &str) -> Result<Self, &'static str> {
+        s.split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|v| !v.is_empty())
+            .try_fold(Self::new(), |variations, v| {
+                let v = rustybuzz::Variation::from_str(v)?;
+                let tag = v.tag.to_bytes();
+                let tag = std::str::from_utf8(&tag).map_err(|_| "Invalid axis tag")?;
+
+                Ok(variations.set(tag, v.value))
+            })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.variations.is_empty()
+    }
+
+    /// Returns a copy of `font` with the axis values applied, its outlines, advances and
+    /// metrics are all interpolated. Axes the font doesn't have are ignored, and fonts that
+    /// aren't variable are returned as is.
+    pub fn apply<'f>(&self, font: &Font<'f>) -> Font<'f> {
+        let face = crate::shaping::face(font);
+        let applies = face.is_variable()
+            && self
+                .variations
+                .iter()
+                .any(|v| face.variation_axes().into_iter().any(|a| a.tag == v.tag));
+
+        if !applies {
+            return font.clone();
+        }
+
+        match font {
+            Font::Ref(face) => {
+                let mut face = face.as_ref().clone();
+                self.variations.iter().for_each(|v| {
+                    face.set_variation(v.tag, v.value);
+                });
+
+                Font::Ref(std::sync::Arc::new(face))
+            }
+            Font::Owned(owned) => {
+                // an owned face can't be cloned, so the data is copied into a new one
+                let data = owned.as_slice().to_vec();
+                let index = collection_index(&data, owned.as_face_ref());
+
+                let mut face = match owned_ttf_parser::OwnedFace::from_vec(data, index) {
+                    Ok(face) => face,
+                    Err(_) => return font.clone(),
+                };
+
+                // keep the coordinates of axes that were set before
+                let previous = owned.as_face_ref().variation_coordinates();
+                face.as_face_ref()
+                    .variation_axes()
+                    .into_iter()
+                    .zip(previous)
+                    .filter(|(_, coord)| coord.get() != 0)
+                    .map(|(axis, coord)| (axis.tag, denormalize(&axis, coord.get())))
+                    .collect::<Vec<_>>()
+                    .into_iter()
+                    .for_each(|(tag, value)| {
+                        face.set_variation(tag, value);
+                    });
+
+                self.variations.iter().for_each(|v| {
+                    face.set_variation(v.tag, v.value);
+                });
+
+                Font::Owned(std::sync::Arc::new(face))
+            }
+        }
+    }
+}
+
+impl FromStr for FontVariations {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+/// Whether `font` is variable with the axis `tag`, e.g. `"wght"`.
+pub(crate) fn has_axis(font: &Font<'_>, tag: &str) -> bool {
+    let tag = owned_ttf_parser::Tag::from_bytes_lossy(tag.as_bytes());
+    let face = crate::shaping::face(font);
+
+    face.is_variable() && face.variation_axes().into_iter().any(|a| a.tag == tag)
+}
+
+/// Finds which font of a collection `face` was loaded from, `0` for single fonts.
+fn collection_index(data: &[u8], face: &owned_ttf_parser::Face) -> u32 {
+    let name = |face: &owned_ttf_parser::Face| {
+        face.names()
+            .into_iter()
+            .find(|n| n.name_id == owned_ttf_parser::name_id::POST_SCRIPT_NAME)
+            .and_then(|n| n.to_string())
+    };
+
+    let count = owned_ttf_parser::fonts_in_collection(data).unwrap_or(1);
+    let target = (name(face), face.number_of_glyphs());
+
+    (0..count)
+        .find(|&i| {
+            owned_ttf_parser::Face::from_slice(data, i)
+                .is_ok_and(|f| (name(&f), f.number_of_glyphs()) == target)
+        })
+        .unwrap_or(0)
+}
+
+/// Maps a normalized coordinate back to the user space value of the axis. `avar` mappings
+/// aren't reversed, which only matters when re-applying axes of an already varied font.
+fn denormalize(axis: &owned_ttf_parser::VariationAxis, coord: i16) -> f32 {
+    let coord = coord as f32 / 16384.0;
+
+    if coord < 0.0 {
+        axis.def_value + coord * (axis.def_value - axis.min_value)
+    } else {
+        axis.def_value + coord * (axis.max_value - axis.def_value)
+    }
+}
+
+#[test]
+fn font_variations_test() {
+    let variations = FontVariations::parse("wght=700, wdth=75 slnt=-10").unwrap();
+
+    assert_eq!(
+        variations,
+        FontVariations::new().weight(700.0).width(75.0).slant(-10.0)
+    );
+
+    assert_eq!(
+        FontVariations::parse("wght=300,wght=800").unwrap(),
+        FontVariations::new().set("wght", 800.0)
+    );
+
+    assert!(FontVariations::parse("wght").is_err());
+}
+
+#[test]
+fn apply_variations_test() {
+    use crate::superfont::{static_test_font, test_font};
+
+    let scale = rusttype::Scale::uniform(100.0);
+    // the advance and ink width of a glyph of `font`
+    let metrics = |font: &Font<'_>| {
+        let glyph = font.glyph('H').scaled(scale);
+        let advance = glyph.h_metrics().advance_width;
+        let bb = glyph.exact_bounding_box().unwrap();
+        (advance, bb.width())
+    };
+
+    let regular = test_font();
+    let (advance, ink) = metrics(&regular);
+    assert!(has_axis(&regular, "wght"));
+    assert!(!has_axis(&regular, "slnt"));
+
+    // the outlines and advances of the CFF2 variable font get heavier and wider
+    let bold = FontVariations::new().weight(800.0).apply(&regular);
+    let (bold_advance, bold_ink) = metrics(&bold);
+    assert!(bold_advance > advance);
+    assert!(bold_ink > ink);
+    assert_eq!(metrics(&regular), (advance, ink));
+
+    // owned fonts are copied with the same axis values
+    let owned = Font::try_from_vec(include_bytes!("../tests/fonts/Cantarell-VF.otf").to_vec())
+        .expect("Failed to load test font");
+    let owned_bold = FontVariations::new().weight(800.0).apply(&owned);
+    assert_eq!(metrics(&owned_bold), (bold_advance, bold_ink));
+
+    // static fonts don't change
+    let font = static_test_font();
+    assert!(!has_axis(&font, "wght"));
+    assert_eq!(
+        metrics(&FontVariations::new().weight(800.0).apply(&font)),
+        metrics(&font)
+    );
+}
@@ -1,21 +1,75 @@
-use crate::superfont::SuperFont;
+use std::collections::{HashMap, VecDeque};
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{
+    hyphenation::{breaks, Break, Language, SOFT_HYPHEN},
+    layout::LayoutOptions,
+    shaping::shape,
+    superfont::SuperFont,
+    tabs::TabStops,
+};
+
+// measures the width of a line of text
+type WidthFn<'a> = dyn Fn(rusttype::Scale, &SuperFont, &str) -> i32 + 'a;
 
 #[derive(Debug, Clone, Copy)]
 pub enum WrapStyle {
     Word,
     Character,
+    /// Breaks at the line break opportunities of the Unicode Line Breaking Algorithm
+    /// (UAX #14): after spaces and hyphens, after slashes in URLs, between CJK ideographs,
+    /// never at non-breaking spaces. Line feeds and other mandatory breaks start a new line.
+    /// Runs of spaces collapse like between words unless the [`WhiteSpace`] mode keeps them.
+    Unicode,
+    /// Breaks between words like `Word`, and hyphenates words that don't fit at the end of
+    /// a line: at soft hyphens (U+00AD) when the word has any, otherwise with the
+    /// hyphenation patterns of the language (`None` only uses soft hyphens).
+    Hyphenate(Option<Language>),
+    /// Breaks between words like `Word`, but picks the breaks for the whole text at once
+    /// (total-fit, as in Knuth–Plass) so that lines have lengths as even as possible,
+    /// instead of filling each line before starting the next.
+    Optimal,
 }
 
+/// How wrapping treats whitespace, like the CSS `white-space` property.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WhiteSpace {
+    /// Runs of whitespace, line breaks included, collapse into single spaces.
+    #[default]
+    Normal,
+    /// Runs of spaces collapse, line breaks start a new line.
+    PreLine,
+    /// Spaces and line breaks are kept and lines aren't wrapped.
+    Pre,
+    /// Spaces and line breaks are kept and lines are wrapped. Spaces at the end of a
+    /// wrapped line don't count towards its width and are removed.
+    PreWrap,
+}
+
+/// Greedily fills lines with words. Every word is shaped once when it's read, the break
+/// is found by adding up the advances of the words, of the spaces between them and the
+/// kerning where they join, then checked by measuring the line with `width_fn`.
 pub struct LineBreaker<'a, W> {
     words: W,
     width: i32,
     font: &'a SuperFont<'a>,
     scale: rusttype::Scale,
 
-    current: Option<String>,
+    // words read ahead, the first one can be the rest of a broken word
+    pending: VecDeque<Word>,
+    // the advance of the space between words, 0 in chars mode
+    space: f32,
+    // the kerning of the grapheme pairs met at the joins of words
+    kerning: HashMap<(char, char), f32>,
     chars_mode: bool,
 
-    width_fn: fn(rusttype::Scale, &SuperFont, &str) -> i32,
+    hyphenate: bool,
+    language: Option<Language>,
+    preserve_spaces: bool,
+    break_words: bool,
+
+    width_fn: Box<WidthFn<'a>>,
 }
 
 impl<'a, W> LineBreaker<'a, W> {
@@ -25,7 +79,7 @@ impl<'a, W> LineBreaker<'a, W> {
         font: &'a SuperFont<'a>,
         scale: rusttype::Scale,
         chars_mode: bool,
-        width_fn: fn(rusttype::Scale, &SuperFont, &str) -> i32,
+        width_fn: impl Fn(rusttype::Scale, &SuperFont, &str) -> i32 + 'a,
     ) -> Self
     where
         W: Iterator<Item = S>,
@@ -37,14 +91,259 @@ impl<'a, W> LineBreaker<'a, W> {
                 width,
                 font,
                 scale,
-                current: None,
+                pending: VecDeque::new(),
+                space: match chars_mode {
+                    true => 0.0,
+                    false => extent(font, scale, " ").0,
+                },
+                kerning: HashMap::new(),
                 chars_mode,
-                width_fn,
+                hyphenate: false,
+                language: None,
+                preserve_spaces: false,
+                break_words: false,
+                width_fn: Box::new(width_fn),
             }
         }
     }
 }
 
+// a word read ahead, with its advance and the right edge of its ink
+struct Word {
+    text: String,
+    advance: f32,
+    ink: f32,
+}
+
+// the kerning between two graphemes, cached when both are single characters
+fn pair_kerning(
+    cache: &mut HashMap<(char, char), f32>,
+    font: &SuperFont,
+    scale: rusttype::Scale,
+    left: &str,
+    right: &str,
+) -> f32 {
+    if left.is_empty() || right.is_empty() {
+        return 0.0;
+    }
+
+    let kerning = || {
+        let advance = |text: &str| extent(font, scale, text).0;
+        advance(&format!("{left}{right}")) - advance(left) - advance(right)
+    };
+
+    let (mut l, mut r) = (left.chars(), right.chars());
+    match (l.next(), l.next(), r.next(), r.next()) {
+        (Some(a), None, Some(b), None) => *cache.entry((a, b)).or_insert_with(kerning),
+        _ => kerning(),
+    }
+}
+
+// the advance of `text` shaped as a line and the right edge of its ink
+fn extent(font: &SuperFont, scale: rusttype::Scale, text: &str) -> (f32, f32) {
+    let (mut caret, mut ink) = (0.0, 0.0f32);
+    for g in shape(font, text, scale, None, None, &TabStops::default()) {
+        if let Some(bb) = g.font.glyph(g.id).scaled(g.scale).exact_bounding_box() {
+            ink = ink.max(caret + g.x_offset + bb.max.x);
+        }
+        caret += g.x_advance;
+    }
+    (caret, ink)
+}
+
+impl<'a, W> LineBreaker<'a, W> {
+    /// Hyphenates words that don't fit at the end of a line, see [`WrapStyle::Hyphenate`].
+    pub fn hyphenate(mut self, language: Option<Language>) -> Self {
+        self.hyphenate = true;
+        self.language = language;
+        self
+    }
+
+    /// Keeps the whitespace at the start of lines. Whitespace at the end of lines is
+    /// still removed, and isn't measured.
+    pub fn preserve_spaces(mut self, preserve: bool) -> Self {
+        self.preserve_spaces = preserve;
+        self
+    }
+
+    /// Breaks words that don't fit on a line of their own between graphemes.
+    pub fn break_words(mut self, break_words: bool) -> Self {
+        self.break_words = break_words;
+        self
+    }
+
+    /// Sets the width of the next lines, so that lines can have different widths.
+    pub fn set_width(&mut self, width: i32) {
+        self.width = width;
+    }
+
+    #[inline]
+    fn trim<'s>(&self, line: &'s str) -> &'s str {
+        if self.preserve_spaces {
+            line.trim_end()
+        } else {
+            line.trim()
+        }
+    }
+
+    #[inline]
+    fn measure(&self, line: &str) -> i32 {
+        (self.width_fn)(self.scale, self.font, self.trim(line))
+    }
+
+    #[inline]
+    fn join(&self, line: &mut String, word: &str) {
+        if !line.is_empty() && !self.chars_mode {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+
+    // the first `n` pending words as a line
+    fn line(&self, n: usize) -> String {
+        let mut line = String::new();
+        for word in self.pending.iter().take(n) {
+            self.join(&mut line, &word.text);
+        }
+        line
+    }
+
+    fn word(&self, text: String) -> Word {
+        let (advance, ink) = extent(self.font, self.scale, &text);
+        Word { text, advance, ink }
+    }
+
+    // the space between pending words `i - 1` and `i`, with the kerning at the join
+    fn gap(&mut self, i: usize) -> f32 {
+        fn edge(text: &str, last: bool) -> &str {
+            let mut graphemes = text.graphemes(true);
+            let edge = if last {
+                graphemes.next_back()
+            } else {
+                graphemes.next()
+            };
+            edge.unwrap_or_default()
+        }
+
+        let (font, scale, cache) = (self.font, self.scale, &mut self.kerning);
+        let mut kerning = |left, right| pair_kerning(cache, font, scale, left, right);
+        let left = edge(&self.pending[i - 1].text, true);
+        let right = edge(&self.pending[i].text, false);
+
+        if self.chars_mode {
+            kerning(left, right)
+        } else {
+            self.space + kerning(left, " ") + kerning(" ", right)
+        }
+    }
+
+    #[inline]
+    fn fits(&self, n: usize) -> bool {
+        self.measure(&self.line(n)) <= self.width
+    }
+
+    // the longest start of `word` that still fits on `line` (with a hyphen) and the rest
+    // of the word
+    fn hyphenate_word(&self, line: &str, word: &str) -> Option<(String, String)> {
+        let with_start = |b: &Break| {
+            let mut new_line = line.to_string();
+            self.join(&mut new_line, &word[..b.end]);
+            if b.hyphen {
+                new_line.push('-');
+            }
+            new_line
+        };
+
+        // the breaks are sorted, so the ones that fit come first
+        let breaks = breaks(word, self.language);
+        let fitting = breaks.partition_point(|b| self.measure(&with_start(b)) <= self.width);
+
+        let b = breaks.get(fitting.checked_sub(1)?)?;
+        Some((with_start(b), word[b.rest..].to_string()))
+    }
+
+    // the longest start of `word` that fits on a line, at least one grapheme, and the rest
+    fn break_word(&self, word: &str) -> Option<(String, String)> {
+        let bounds = word
+            .grapheme_indices(true)
+            .map(|(i, _)| i)
+            .skip(1)
+            .collect::<Vec<_>>();
+
+        // `bounds[lo - 1]` is known to fit (or is the first grapheme), `bounds[hi - 1]` not
+        let (mut lo, mut hi) = (1, bounds.len() + 1);
+        while hi - lo > 1 {
+            let mid = (lo + hi) / 2;
+            if self.measure(&word[..bounds[mid - 1]]) <= self.width {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+
+        let end = *bounds.get(lo - 1)?;
+        Some((word[..end].to_string(), word[end..].to_string()))
+    }
+}
+
+impl<'a, W, S> LineBreaker<'a, W>
+where
+    W: Iterator<Item = S>,
+    S: AsRef<str>,
+{
+    // makes sure that `n` words are pending, false when there aren't that many left
+    fn fill(&mut self, n: usize) -> bool {
+        while self.pending.len() < n {
+            match self.words.next() {
+                Some(word) => {
+                    let word = self.word(word.as_ref().to_string());
+                    self.pending.push_back(word);
+                }
+                None => return false,
+            }
+        }
+        true
+    }
+
+    // whether the next word fits on a line of the current width without being broken
+    pub(crate) fn next_fits(&mut self) -> bool {
+        !self.fill(1) || self.fits(1)
+    }
+
+    // the most pending words that fit on a line, 0 when the first word doesn't fit. There
+    // is at least one pending word.
+    fn fitting(&mut self) -> usize {
+        let width = self.width as f32;
+
+        // the words whose ink ends before the width, adding up their advances
+        let (mut n, mut caret) = (0, 0.0);
+        while self.fill(n + 1) {
+            if n > 0 {
+                caret += self.gap(n);
+            }
+            if (caret + self.pending[n].ink).ceil() > width {
+                break;
+            }
+            caret += self.pending[n].advance;
+            n += 1;
+        }
+
+        // the measured width can be off by a pixel of rounding, or more with a `width_fn`
+        // that doesn't measure the line as it's shaped
+        if n > 0 && !self.fits(n) {
+            n -= 1;
+            while n > 0 && !self.fits(n) {
+                n -= 1;
+            }
+        } else {
+            while self.fill(n + 1) && self.fits(n + 1) {
+                n += 1;
+            }
+        }
+        n
+    }
+}
+
 impl<'a, W, S> Iterator for LineBreaker<'a, W>
 where
     W: Iterator<Item = S>,
@@ -53,36 +352,49 @@ where
     type Item = String;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let mut line = self.current.take().unwrap_or_else(String::new);
+        if !self.fill(1) {
+            return None;
+        }
 
-        while let Some(word) = self.words.next() {
-            let word = word.as_ref();
+        let n = self.fitting();
 
-            let mut new_line = line.clone();
-            if !new_line.is_empty() && !self.chars_mode {
-                new_line.push(' ');
-            }
-            new_line.push_str(word);
+        // a word that doesn't fit on its own gets a line of its own, or is broken
+        if n == 0 {
+            let word = self.pending.pop_front()?.text;
 
-            let w = (self.width_fn)(self.scale, &self.font, &new_line);
+            let broken = match (self.hyphenate, self.break_words) {
+                (true, _) => self.hyphenate_word("", &word),
+                (_, true) => self.break_word(&word),
+                _ => None,
+            };
 
-            if w > self.width {
-                if !line.is_empty() {
-                    self.current = Some(word.to_string());
-                    return Some(line);
+            return Some(match broken {
+                Some((line, rest)) => {
+                    let rest = self.word(rest);
+                    self.pending.push_front(rest);
+                    self.trim(&line).to_string()
                 }
-                line = word.to_string();
-            } else {
-                line = new_line;
+                None => self.trim(&word).to_string(),
+            });
+        }
+
+        if self.hyphenate && self.fill(n + 1) {
+            if let Some((line, rest)) = self.hyphenate_word(&self.line(n), &self.pending[n].text) {
+                let rest = self.word(rest);
+                self.pending.drain(..=n);
+                self.pending.push_front(rest);
+                return Some(self.trim(&line).to_string());
             }
         }
 
-        line = line.trim().to_string();
+        let line = self.line(n);
+        self.pending.drain(..n);
 
-        if !line.is_empty() {
-            Some(line)
-        } else {
+        let line = self.trim(&line).to_string();
+        if line.is_empty() && !self.fill(1) {
             None
+        } else {
+            Some(line)
         }
     }
 }
@@ -94,7 +406,7 @@ pub trait Wrappable: Iterator {
         font: &'a SuperFont<'a>,
         scale: rusttype::Scale,
         chars_mode: bool,
-        size_fn: fn(rusttype::Scale, &SuperFont, &str) -> i32,
+        size_fn: impl Fn(rusttype::Scale, &SuperFont, &str) -> i32 + 'a,
     ) -> LineBreaker<'a, Self>
     where
         Self: Sized,
@@ -106,6 +418,7 @@ pub trait Wrappable: Iterator {
 
 impl<T, S> Wrappable for T where T: Iterator<Item = S> {}
 
+/// Wraps `text` into lines that fit in `width`, see [`WrapStyle`].
 pub fn text_wrap(
     text: &str,
     width: i32,
@@ -114,33 +427,481 @@ pub fn text_wrap(
     wrap_style: WrapStyle,
     width_fn: fn(rusttype::Scale, &SuperFont, &str) -> i32,
 ) -> Vec<String> {
+    text_wrap_with_options(
+        text,
+        width,
+        font,
+        scale,
+        wrap_style,
+        &LayoutOptions::default(),
+        width_fn,
+    )
+}
+
+/// Same as [`text_wrap`], handling whitespace according to the [`WhiteSpace`] mode of
+/// `options`. `width_fn` measures lines, tabs included.
+pub fn text_wrap_with_options(
+    text: &str,
+    width: i32,
+    font: &SuperFont,
+    scale: rusttype::Scale,
+    wrap_style: WrapStyle,
+    options: &LayoutOptions,
+    width_fn: impl Fn(rusttype::Scale, &SuperFont, &str) -> i32,
+) -> Vec<String> {
+    wrap_lines_limited(
+        text,
+        width,
+        usize::MAX,
+        font,
+        scale,
+        wrap_style,
+        options,
+        &width_fn,
+    )
+}
+
+// same as `text_wrap_with_options`, stopping once `max_lines` lines are wrapped
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn wrap_lines_limited(
+    text: &str,
+    width: i32,
+    max_lines: usize,
+    font: &SuperFont,
+    scale: rusttype::Scale,
+    wrap_style: WrapStyle,
+    options: &LayoutOptions,
+    width_fn: &WidthFn,
+) -> Vec<String> {
+    let preserve = match options.white_space {
+        WhiteSpace::Normal => {
+            return wrap(
+                text, width, max_lines, font, scale, wrap_style, false, width_fn,
+            )
+        }
+        WhiteSpace::Pre => {
+            return paragraphs(text)
+                .take(max_lines)
+                .map(str::to_string)
+                .collect()
+        }
+        WhiteSpace::PreLine => false,
+        WhiteSpace::PreWrap => true,
+    };
+
+    let mut lines = Vec::new();
+    for paragraph in paragraphs(text) {
+        if lines.len() >= max_lines {
+            break;
+        }
+
+        let wrapped = wrap(
+            paragraph,
+            width,
+            max_lines - lines.len(),
+            font,
+            scale,
+            wrap_style,
+            preserve,
+            width_fn,
+        );
+        // an empty line keeps its height
+        if wrapped.is_empty() {
+            lines.push(String::new());
+        } else {
+            lines.extend(wrapped);
+        }
+    }
+    lines
+}
+
+// the lines of `text`, without their line break
+pub(crate) fn paragraphs(text: &str) -> impl Iterator<Item = &str> {
+    text.split('\n').map(|p| p.strip_suffix('\r').unwrap_or(p))
+}
+
+// words with the whitespace after them, joining them gives the text back. Whitespace at
+// the start of the text belongs to the first word.
+pub(crate) fn spaced_words(text: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut start = 0;
+    let mut space = false;
+
+    for (i, c) in text.char_indices() {
+        if space && !c.is_whitespace() && !text[start..i].trim().is_empty() {
+            words.push(&text[start..i]);
+            start = i;
+        }
+        space = c.is_whitespace();
+    }
+
+    if start < text.len() {
+        words.push(&text[start..]);
+    }
+    words
+}
+
+// wraps a paragraph into at most `max_lines` lines, `preserve` keeps its whitespace
+#[allow(clippy::too_many_arguments)]
+fn wrap(
+    text: &str,
+    width: i32,
+    max_lines: usize,
+    font: &SuperFont,
+    scale: rusttype::Scale,
+    wrap_style: WrapStyle,
+    preserve: bool,
+    width_fn: &WidthFn,
+) -> Vec<String> {
+    let words = if preserve {
+        spaced_words(text)
+    } else {
+        text.split_whitespace().collect()
+    };
+
     match wrap_style {
-        WrapStyle::Word => text
-            .split_whitespace()
+        WrapStyle::Word => words
+            .into_iter()
+            .wrap_lines(width, font, scale, preserve, width_fn)
+            .preserve_spaces(preserve)
+            .take(max_lines)
+            .collect(),
+        WrapStyle::Character if preserve => text
+            .graphemes(true)
+            .wrap_lines(width, font, scale, true, width_fn)
+            .preserve_spaces(true)
+            .take(max_lines)
+            .collect(),
+        WrapStyle::Character => words
+            .into_iter()
             .wrap_lines(width, font, scale, false, width_fn)
+            .break_words(true)
+            .take(max_lines)
             .collect(),
-        WrapStyle::Character => {
+        WrapStyle::Unicode => {
+            let collapsed;
+            let text = match preserve {
+                true => text,
+                false => {
+                    collapsed = collapse_spaces(text);
+                    collapsed.as_str()
+                }
+            };
+
             let mut result = Vec::new();
-            for line in text
-                .split_whitespace()
-                .wrap_lines(width, font, scale, false, width_fn)
-            {
-                let w = (width_fn)(scale, font, &line);
-                if w > width {
-                    unicode_segmentation::UnicodeSegmentation::graphemes(line.as_str(), true)
-                        .wrap_lines(width, font, scale, true, width_fn)
-                        .for_each(|l| result.push(l));
-                } else {
-                    result.push(line);
+            let mut segments = Vec::new();
+            let mut start = 0;
+
+            // segments end at break opportunities and keep their trailing spaces, so they
+            // are joined without a separator
+            for (end, opportunity) in unicode_linebreak::linebreaks(text) {
+                segments.push(&text[start..end]);
+                start = end;
+
+                if opportunity == unicode_linebreak::BreakOpportunity::Mandatory {
+                    result.extend(
+                        segments
+                            .drain(..)
+                            .wrap_lines(width, font, scale, true, width_fn)
+                            .preserve_spaces(preserve),
+                    );
+                    if result.len() >= max_lines {
+                        break;
+                    }
                 }
             }
 
-            // final pass to clean up any character broken lines
-            result
-                .join(" ")
-                .split_whitespace()
-                .wrap_lines(width, font, scale, false, width_fn)
-                .collect::<Vec<String>>()
+            result.truncate(max_lines);
+            result.into_iter().map(soft_hyphens).collect()
+        }
+        WrapStyle::Hyphenate(language) => words
+            .into_iter()
+            .wrap_lines(width, font, scale, preserve, width_fn)
+            .preserve_spaces(preserve)
+            .hyphenate(language)
+            .map(soft_hyphens)
+            .take(max_lines)
+            .collect(),
+        // the breaks depend on the whole paragraph
+        WrapStyle::Optimal => {
+            let mut lines = optimal_wrap(
+                &words,
+                if preserve { "" } else { " " },
+                width,
+                font,
+                scale,
+                width_fn,
+            );
+            lines.truncate(max_lines);
+            lines
         }
     }
 }
+
+// total-fit line breaking: the breaks minimize the sum of the squared free space of every
+// line but the last. A word wider than `width` gets a line of its own.
+fn optimal_wrap(
+    words: &[&str],
+    separator: &str,
+    width: i32,
+    font: &SuperFont,
+    scale: rusttype::Scale,
+    width_fn: &WidthFn,
+) -> Vec<String> {
+    // best[j] is the lowest cost of breaking the first `j` words into lines, and the word
+    // that starts the last of these lines
+    let mut best = vec![(f64::INFINITY, 0); words.len() + 1];
+    best[0].0 = 0.0;
+
+    for start in 0..words.len() {
+        let mut line = String::new();
+
+        for end in start..words.len() {
+            if end > start {
+                line.push_str(separator);
+            }
+            line.push_str(words[end]);
+
+            let w = width_fn(scale, font, line.trim_end());
+            if w > width && end > start {
+                break;
+            }
+
+            let free = (width - w).max(0) as f64;
+            let cost = if end + 1 == words.len() {
+                0.0
+            } else {
+                free * free
+            };
+
+            if best[start].0 + cost < best[end + 1].0 {
+                best[end + 1] = (best[start].0 + cost, start);
+            }
+        }
+    }
+
+    let mut lines = Vec::new();
+    let mut end = words.len();
+    while end > 0 {
+        let start = best[end].1;
+        lines.push(words[start..end].join(separator).trim_end().to_string());
+        end = start;
+    }
+
+    lines.reverse();
+    lines
+}
+
+// runs of spaces and tabs as a single space, like splitting the text into words does.
+// Line breaks and no-break spaces are kept.
+pub(crate) fn collapse_spaces(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            ' ' | '\t' if collapsed.ends_with(' ') => {}
+            ' ' | '\t' => collapsed.push(' '),
+            c => collapsed.push(c),
+        }
+    }
+    collapsed
+}
+
+// a line broken at a soft hyphen shows a hyphen, the other soft hyphens aren't drawn
+pub(crate) fn soft_hyphens(line: String) -> String {
+    let mut line = match line.strip_suffix(SOFT_HYPHEN) {
+        Some(line) => format!("{line}-"),
+        None => line,
+    };
+    line.retain(|c| c != SOFT_HYPHEN);
+    line
+}
+
+#[cfg(feature = "emoji")]
+#[test]
+fn unicode_wrap_test() {
+    let font = SuperFont::new(crate::emoji::EMOJI_FONT.clone(), vec![]);
+    let scale = rusttype::Scale::uniform(20.0);
+    let width = crate::measure::text_width(scale, &font, "1111");
+
+    let wrap = |text| {
+        text_wrap(
+            text,
+            width,
+            &font,
+            scale,
+            WrapStyle::Unicode,
+            crate::measure::text_width,
+        )
+    };
+
+    assert_eq!(wrap("11 22 33"), vec!["11", "22", "33"]);
+    // no break at a non-breaking space
+    assert_eq!(wrap("1\u{a0}1 2"), vec!["1\u{a0}1", "2"]);
+    // a zero width space is a break opportunity
+    assert_eq!(wrap("111\u{200b}222"), vec!["111\u{200b}", "222"]);
+    // line feeds are mandatory breaks
+    assert_eq!(wrap("1\n2"), vec!["1", "2"]);
+    // spaces collapse like between words
+    assert_eq!(wrap("1   1\t 22"), vec!["1 1", "22"]);
+    assert_eq!(wrap("1 \n  2"), vec!["1", "2"]);
+}
+
+#[cfg(feature = "emoji")]
+#[test]
+fn hyphenate_wrap_test() {
+    let font = SuperFont::new(crate::emoji::EMOJI_FONT.clone(), vec![]);
+    let scale = rusttype::Scale::uniform(20.0);
+    let width = crate::measure::text_width(scale, &font, "11 2222");
+
+    let wrap =
+        |text, style| text_wrap(text, width, &font, scale, style, crate::measure::text_width);
+
+    let text = "11 22\u{ad}33\u{ad}4444";
+    assert_eq!(
+        wrap(text, WrapStyle::Hyphenate(None)),
+        vec!["11 22-", "334444"]
+    );
+    assert_eq!(wrap(text, WrapStyle::Word), vec!["11", &text[3..]]);
+    assert_eq!(wrap(text, WrapStyle::Unicode), vec!["11 2233-", "4444"]);
+}
+
+#[cfg(feature = "emoji")]
+#[test]
+fn optimal_wrap_test() {
+    let font = SuperFont::new(crate::emoji::EMOJI_FONT.clone(), vec![]);
+    let scale = rusttype::Scale::uniform(20.0);
+    let width = crate::measure::text_width(scale, &font, "111 22");
+
+    let wrap = |style| {
+        text_wrap(
+            "111 22 33 44444",
+            width,
+            &font,
+            scale,
+            style,
+            crate::measure::text_width,
+        )
+    };
+
+    assert_eq!(wrap(WrapStyle::Word), vec!["111 22", "33", "44444"]);
+    assert_eq!(wrap(WrapStyle::Optimal), vec!["111", "22 33", "44444"]);
+
+    let lines = text_wrap(
+        "1 22222222 1",
+        width,
+        &font,
+        scale,
+        WrapStyle::Optimal,
+        crate::measure::text_width,
+    );
+    assert_eq!(lines, vec!["1", "22222222", "1"]);
+    assert!(text_wrap(
+        "",
+        width,
+        &font,
+        scale,
+        WrapStyle::Optimal,
+        crate::measure::text_width
+    )
+    .is_empty());
+}
+
+#[cfg(feature = "emoji")]
+#[test]
+fn white_space_test() {
+    let font = SuperFont::new(crate::emoji::EMOJI_FONT.clone(), vec![]);
+    let scale = rusttype::Scale::uniform(20.0);
+    let width = crate::measure::text_width(scale, &font, "11 22");
+    let text = "11 22 33\r\n\n  44   55";
+
+    let wrap = |white_space| {
+        text_wrap_with_options(
+            text,
+            width,
+            &font,
+            scale,
+            WrapStyle::Word,
+            &LayoutOptions::new().with_white_space(white_space),
+            crate::measure::text_width,
+        )
+    };
+
+    assert_eq!(wrap(WhiteSpace::Normal), vec!["11 22", "33", "44 55"]);
+    assert_eq!(wrap(WhiteSpace::PreLine), vec!["11 22", "33", "", "44 55"]);
+    assert_eq!(wrap(WhiteSpace::Pre), vec!["11 22 33", "", "  44   55"]);
+    assert_eq!(
+        wrap(WhiteSpace::PreWrap),
+        vec!["11 22", "33", "", "  44", "55"]
+    );
+}
+
+#[cfg(feature = "emoji")]
+#[test]
+fn line_breaker_test() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let font = SuperFont::new(crate::emoji::EMOJI_FONT.clone(), vec![]);
+    let scale = rusttype::Scale::uniform(20.0);
+    let words = "11 2 333 1 22 4444 1 1 1 22 333 1 22222222 1 22"
+        .split(' ')
+        .collect::<Vec<_>>();
+
+    // filling lines word by word
+    let greedy = |font: &SuperFont, words: &[&str], width| {
+        let mut lines = Vec::new();
+        let mut line = String::new();
+        for word in words {
+            let new_line = format!("{line} {word}").trim().to_string();
+            if crate::measure::text_width(scale, font, &new_line) > width && !line.is_empty() {
+                lines.push(std::mem::replace(&mut line, word.to_string()));
+            } else {
+                line = new_line;
+            }
+        }
+        lines.push(line);
+        lines
+    };
+
+    for width in (0..400).step_by(7) {
+        let lines = words
+            .iter()
+            .wrap_lines(width, &font, scale, false, crate::measure::text_width)
+            .collect::<Vec<_>>();
+        assert_eq!(lines, greedy(&font, &words, width), "width {width}");
+    }
+
+    // a long kerned text wraps the same, with a couple of measures per line
+    static MEASURES: AtomicUsize = AtomicUsize::new(0);
+    fn counted_width(scale: rusttype::Scale, font: &SuperFont, text: &str) -> i32 {
+        MEASURES.fetch_add(1, Ordering::Relaxed);
+        crate::measure::text_width(scale, font, text)
+    }
+
+    let font = SuperFont::new(crate::superfont::static_test_font(), vec![]);
+    let vocabulary = [
+        "To", "AVA", "fly", "Ṫo", "LT", "y.", "Wave", "of", "Tea", "Yo,",
+    ];
+    let mut seed = 7u32;
+    let words = (0..500)
+        .map(|_| {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            vocabulary[(seed >> 16) as usize % vocabulary.len()]
+        })
+        .collect::<Vec<_>>();
+
+    for width in [90, 400] {
+        MEASURES.store(0, Ordering::Relaxed);
+        let lines = words
+            .iter()
+            .wrap_lines(width, &font, scale, false, counted_width)
+            .collect::<Vec<_>>();
+        let measures = MEASURES.load(Ordering::Relaxed);
+
+        assert_eq!(lines, greedy(&font, &words, width), "width {width}");
+        assert!(
+            measures <= 2 * lines.len() + 2,
+            "width {width}, {measures} measures for {} lines",
+            lines.len()
+        );
+    }
+}